@@ -0,0 +1,328 @@
+use regex::Regex;
+use rust_decimal::Decimal;
+use std::{path::Path, str::FromStr};
+
+use crate::llm::models::{DocumentInfo, MonetaryAmount};
+use crate::llm::{read_json_file, save_json_file};
+use crate::text::normalize;
+use crate::transcription::{text_lines, xml_path_for, TextLine};
+
+const MAX_LABEL_LENGTH: usize = 60;
+
+/// Normalized label prefixes mapped to the keys used for reporting and naming. The longest
+/// matching prefix wins, so that "VALOR TOTAL DOS PRODUTOS" is not taken for "VALOR TOTAL".
+const KNOWN_LABELS: &[(&str, &str)] = &[
+    ("VALOR TOTAL DA NOTA", "valor_total"),
+    ("VALOR TOTAL DOS PRODUTOS", "valor_produtos"),
+    ("VALOR TOTAL DOS SERVICOS", "valor_servicos"),
+    ("VALOR TOTAL DO ICMS", "icms"),
+    ("VALOR TOTAL DO IPI", "ipi"),
+    ("VALOR TOTAL DOS TRIBUTOS", "tributos"),
+    ("VALOR APROXIMADO DOS TRIBUTOS", "tributos"),
+    ("VALOR APROX DOS TRIBUTOS", "tributos"),
+    ("VALOR TOTAL", "valor_total"),
+    ("TOTAL A PAGAR", "valor_total"),
+    ("VALOR DO DOCUMENTO", "valor_total"),
+    ("VALOR COBRADO", "valor_total"),
+    ("VALOR LIQUIDO", "valor_liquido"),
+    ("VALOR BRUTO", "valor_bruto"),
+    ("VALOR DOS SERVICOS", "valor_servicos"),
+    ("VALOR DOS PRODUTOS", "valor_produtos"),
+    ("TOTAL DOS PRODUTOS", "valor_produtos"),
+    ("BASE DE CALCULO DO ICMS", "base_icms"),
+    ("BASE DE CALCULO DO ICMS ST", "base_icms_st"),
+    ("VALOR DO ICMS SUBSTITUICAO", "icms_st"),
+    ("VALOR DO ICMS ST", "icms_st"),
+    ("VALOR DO ICMS", "icms"),
+    ("ICMS", "icms"),
+    ("VALOR DO IPI", "ipi"),
+    ("IPI", "ipi"),
+    ("ISS", "iss"),
+    ("PIS", "pis"),
+    ("COFINS", "cofins"),
+    ("IRRF", "irrf"),
+    ("CSLL", "csll"),
+    ("INSS", "inss"),
+    ("DESCONTO", "desconto"),
+    ("VALOR DO FRETE", "frete"),
+    ("VALOR DO SEGURO", "seguro"),
+    ("OUTRAS DESPESAS", "outras_despesas"),
+    ("FRETE", "frete"),
+    ("JUROS", "juros"),
+    ("MULTA", "multa"),
+    ("CAPITAL SOCIAL", "capital_social"),
+    ("TOTAL", "valor_total"),
+];
+
+/// Parses a Brazilian formatted amount such as `R$ 1.234,56`, `-R$ 50,00` or `(1.234,56)`.
+pub fn parse_brl(value: &str) -> Option<Decimal> {
+    let value = value.replace("R$", "");
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    // Statements write debits in parentheses.
+    let (value, negative) = match value
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+    {
+        Some(inner) => (inner, true),
+        None => (value.as_str(), false),
+    };
+    if value.is_empty() {
+        return None;
+    }
+
+    let normalized = if value.contains(',') {
+        value.replace('.', "").replace(',', ".")
+    } else {
+        // Without a decimal comma, dots can only be thousands separators.
+        value.replace('.', "")
+    };
+    let amount = Decimal::from_str(&normalized).ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// Formats an amount the Brazilian way, e.g. `1.234,56`.
+pub fn format_brl(value: &Decimal) -> String {
+    let value = value.round_dp(2);
+    let text = format!("{:.2}", value.abs());
+    let (integer, fraction) = text.split_once('.').unwrap_or((&text, "00"));
+
+    let mut grouped = String::new();
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push('.');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if value.is_sign_negative() && !value.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    format!("{}{},{}", sign, grouped, fraction)
+}
+
+fn label_key(label: &str) -> Option<String> {
+    let normalized = normalize(label);
+    KNOWN_LABELS
+        .iter()
+        .filter(|(prefix, _)| normalized.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, key)| key.to_string())
+}
+
+fn clean_label(label: &str) -> String {
+    label
+        .trim()
+        .trim_end_matches(|c: char| c == ':' || c == '-' || c == '=' || c.is_whitespace())
+        .trim()
+        .to_string()
+}
+
+/// Extracts the monetary amounts of a transcription together with the label that precedes them,
+/// either on the same text node or on the previous one (e.g. a table cell). Amounts with a minus
+/// sign, also before the currency, or in parentheses are negative. Percentages, such as tax
+/// rates, are not amounts.
+pub fn extract_amounts(lines: &[TextLine]) -> Vec<MonetaryAmount> {
+    let re = Regex::new(r"(\()?(-)?(R\$\s*)?(-?\d{1,3}(?:\.\d{3})+,\d{2}|-?\d+,\d{2})\b(\))?")
+        .expect("Regex should never fail");
+    let mut amounts = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let mut label_start = 0;
+        for captures in re.captures_iter(&line.text) {
+            let whole = captures.get(0).expect("Regex match has a group 0");
+            // The rate stays part of the label of the amount that follows it.
+            if line.text[whole.end()..].trim_start().starts_with('%') {
+                continue;
+            }
+            let has_currency = captures.get(3).is_some();
+            let negative = captures.get(2).is_some()
+                || (captures.get(1).is_some() && captures.get(5).is_some());
+
+            let mut label = clean_label(&line.text[label_start..whole.start()]);
+            label_start = whole.end();
+            if label.is_empty() && index > 0 {
+                let previous = &lines[index - 1];
+                if previous.page == line.page
+                    && previous.text.len() <= MAX_LABEL_LENGTH
+                    && !re.is_match(&previous.text)
+                {
+                    label = clean_label(&previous.text);
+                }
+            }
+            if label.chars().count() > MAX_LABEL_LENGTH {
+                let skip = label.chars().count() - MAX_LABEL_LENGTH;
+                label = clean_label(&label.chars().skip(skip).collect::<String>());
+            }
+
+            let key = label_key(&label);
+            if !has_currency && key.is_none() {
+                continue;
+            }
+
+            if let Some(value) = parse_brl(&captures[4]) {
+                amounts.push(MonetaryAmount {
+                    label,
+                    key,
+                    value: if negative { -value } else { value },
+                    page: line.page,
+                });
+            }
+        }
+    }
+
+    amounts
+}
+
+pub fn extract_amounts_from_xml(xml: &str) -> Result<Vec<MonetaryAmount>, String> {
+    Ok(extract_amounts(&text_lines(xml)?))
+}
+
+/// The main amount of a document, preferring the total over the net value.
+pub fn main_amount(document_info: &DocumentInfo) -> Option<&MonetaryAmount> {
+    ["valor_total", "valor_liquido"].iter().find_map(|key| {
+        document_info
+            .amounts
+            .iter()
+            .find(|amount| amount.key.as_deref() == Some(key))
+    })
+}
+
+/// Extracts the amounts of an existing document from its cached transcription.
+#[tauri::command]
+pub fn extract_document_amounts(path: String) -> Result<DocumentInfo, String> {
+    let json_path = Path::new(&path);
+    let mut document_info = read_json_file(json_path)?;
+    let xml = std::fs::read_to_string(xml_path_for(json_path))
+        .map_err(|e| format!("Failed to read transcription: {}", e))?;
+    document_info.amounts = extract_amounts_from_xml(&xml)?;
+
+    let serialized_json = serde_json::to_string(&document_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    save_json_file(&serialized_json, json_path)?;
+    Ok(document_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn lines(texts: &[&str]) -> Vec<TextLine> {
+        texts
+            .iter()
+            .map(|text| TextLine {
+                page: Some(1),
+                text: text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_brazilian_amounts() {
+        for (text, expected) in [
+            ("1.234,56", "1234.56"),
+            ("R$ 0,50", "0.50"),
+            ("R$1.234.567,89", "1234567.89"),
+            ("1234,5", "1234.5"),
+            ("1.234", "1234"),
+            ("-1.234,56", "-1234.56"),
+            ("-R$ 50,00", "-50.00"),
+            ("R$ -50,00", "-50.00"),
+            ("(1.234,56)", "-1234.56"),
+            ("(R$ 0,50)", "-0.50"),
+        ] {
+            assert_eq!(parse_brl(text), Some(amount(expected)), "{}", text);
+        }
+        for text in ["", "R$", "()", "abc", "1,234,56"] {
+            assert_eq!(parse_brl(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn formats_brazilian_amounts() {
+        assert_eq!(format_brl(&amount("1234567.891")), "1.234.567,89");
+        assert_eq!(format_brl(&amount("0.5")), "0,50");
+        assert_eq!(format_brl(&amount("-1234.56")), "-1.234,56");
+    }
+
+    #[test]
+    fn takes_the_invoice_total_of_a_danfe() {
+        let amounts = extract_amounts(&lines(&[
+            "CÁLCULO DO IMPOSTO",
+            "BASE DE CÁLCULO DO ICMS",
+            "1.000,00",
+            "VALOR DO ICMS",
+            "180,00",
+            "VALOR TOTAL DOS PRODUTOS",
+            "1.000,00",
+            "VALOR DO FRETE",
+            "50,00",
+            "VALOR TOTAL DO IPI",
+            "100,00",
+            "VALOR TOTAL DA NOTA",
+            "1.150,00",
+            "VALOR APROX. DOS TRIBUTOS R$ 300,00",
+        ]));
+        let keys: Vec<(Option<&str>, Decimal)> = amounts
+            .iter()
+            .map(|amount| (amount.key.as_deref(), amount.value))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Some("base_icms"), amount("1000.00")),
+                (Some("icms"), amount("180.00")),
+                (Some("valor_produtos"), amount("1000.00")),
+                (Some("frete"), amount("50.00")),
+                (Some("ipi"), amount("100.00")),
+                (Some("valor_total"), amount("1150.00")),
+                (Some("tributos"), amount("300.00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_percentages() {
+        assert!(extract_amounts(&lines(&["ICMS 18,00%", "ALÍQ. IPI 5,00 %"])).is_empty());
+
+        let amounts = extract_amounts(&lines(&["ICMS 18,00% R$ 180,00"]));
+        assert_eq!(amounts.len(), 1);
+        assert_eq!(amounts[0].key.as_deref(), Some("icms"));
+        assert_eq!(amounts[0].value, amount("180.00"));
+    }
+
+    #[test]
+    fn extracts_labelled_amounts() {
+        let amounts = extract_amounts(&lines(&[
+            "VALOR TOTAL DA NOTA: R$ 1.234,56",
+            "Desconto",
+            "(50,00)",
+            "Juros -R$ 0,50 Multa R$ 2,00",
+            "Quantidade 3,00",
+            "Saldo anterior (R$ 10,00)",
+        ]));
+        let found: Vec<(&str, Option<&str>, Decimal)> = amounts
+            .iter()
+            .map(|amount| (amount.label.as_str(), amount.key.as_deref(), amount.value))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "VALOR TOTAL DA NOTA",
+                    Some("valor_total"),
+                    amount("1234.56")
+                ),
+                ("Desconto", Some("desconto"), amount("-50.00")),
+                ("Juros", Some("juros"), amount("-0.50")),
+                ("Multa", Some("multa"), amount("2.00")),
+                ("Saldo anterior", None, amount("-10.00")),
+            ]
+        );
+    }
+}
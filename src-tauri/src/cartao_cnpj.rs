@@ -0,0 +1,365 @@
+use regex::Regex;
+use std::{fs, path::Path};
+
+use crate::entities::{is_valid_cnpj, load_registry, save_registry};
+use crate::extraction::schemas::{Activity, CartaoCnpjData, StructuredData};
+use crate::llm::models::DocumentInfo;
+use crate::llm::{read_json_file, save_json_file};
+use crate::text::{digits_only, normalize, stands_alone};
+use crate::transcription::{text_lines, xml_path_for};
+
+pub const DOCUMENT_TYPE: &str = "cartao_cnpj";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Cnpj,
+    OpeningDate,
+    CompanyName,
+    TradeName,
+    Size,
+    MainActivity,
+    SecondaryActivities,
+    LegalNature,
+    Street,
+    Number,
+    Complement,
+    PostalCode,
+    District,
+    City,
+    State,
+    Email,
+    Phone,
+    Status,
+    StatusDate,
+    Ignored,
+}
+
+/// Labels of the fixed layout of the cartão CNPJ, normalized.
+const LABELS: &[(&str, Field)] = &[
+    ("NUMERO DE INSCRICAO", Field::Cnpj),
+    ("DATA DE ABERTURA", Field::OpeningDate),
+    ("NOME EMPRESARIAL", Field::CompanyName),
+    (
+        "TITULO DO ESTABELECIMENTO NOME DE FANTASIA",
+        Field::TradeName,
+    ),
+    ("PORTE", Field::Size),
+    (
+        "CODIGO E DESCRICAO DA ATIVIDADE ECONOMICA PRINCIPAL",
+        Field::MainActivity,
+    ),
+    (
+        "CODIGO E DESCRICAO DAS ATIVIDADES ECONOMICAS SECUNDARIAS",
+        Field::SecondaryActivities,
+    ),
+    (
+        "CODIGO E DESCRICAO DA NATUREZA JURIDICA",
+        Field::LegalNature,
+    ),
+    ("LOGRADOURO", Field::Street),
+    ("NUMERO", Field::Number),
+    ("COMPLEMENTO", Field::Complement),
+    ("CEP", Field::PostalCode),
+    ("BAIRRO DISTRITO", Field::District),
+    ("MUNICIPIO", Field::City),
+    ("UF", Field::State),
+    ("ENDERECO ELETRONICO", Field::Email),
+    ("TELEFONE", Field::Phone),
+    ("ENTE FEDERATIVO RESPONSAVEL EFR", Field::Ignored),
+    ("SITUACAO CADASTRAL", Field::Status),
+    ("DATA DA SITUACAO CADASTRAL", Field::StatusDate),
+    ("MOTIVO DE SITUACAO CADASTRAL", Field::Ignored),
+    ("SITUACAO ESPECIAL", Field::Ignored),
+    ("DATA DA SITUACAO ESPECIAL", Field::Ignored),
+];
+
+/// Recognizes a label line, returning the field and the value written after it, if any.
+fn match_label(text: &str) -> Option<(Field, String)> {
+    let normalized = normalize(text);
+    let (label, field) = LABELS
+        .iter()
+        .filter(|(label, _)| normalized == *label || normalized.starts_with(&format!("{} ", label)))
+        .max_by_key(|(label, _)| label.len())?;
+
+    if normalized == *label {
+        return Some((*field, String::new()));
+    }
+
+    // Inline values only count when separated by a colon, so that values that happen to start
+    // with a label word (e.g. "NUMERO UM LTDA") are not taken for labels.
+    let label_tokens = label.split(' ').count();
+    let mut seen_tokens = 0;
+    let mut in_token = false;
+    for (index, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            in_token = true;
+        } else if in_token {
+            in_token = false;
+            seen_tokens += 1;
+            if seen_tokens == label_tokens {
+                let rest =
+                    text[index..].trim_start_matches(|c: char| c != ':' && !c.is_alphanumeric());
+                return rest
+                    .strip_prefix(':')
+                    .map(|value| (*field, value.trim().to_string()));
+            }
+        }
+    }
+    None
+}
+
+fn clean_value(value: &str) -> String {
+    let value = value.trim();
+    if value.chars().all(|c| c == '*' || c.is_whitespace()) {
+        return String::new();
+    }
+    value.to_string()
+}
+
+fn parse_activity(value: &str) -> Activity {
+    let re = Regex::new(r"^(\d{2}\.\d{2}-\d-\d{2}|\d{3}-\d)\s*-?\s*(.*)$")
+        .expect("Regex should never fail");
+    match re.captures(value.trim()) {
+        Some(captures) => Activity {
+            code: captures[1].to_string(),
+            description: captures[2].trim().to_string(),
+        },
+        None => Activity {
+            code: String::new(),
+            description: value.trim().to_string(),
+        },
+    }
+}
+
+fn assign(record: &mut CartaoCnpjData, field: Field, value: &str) {
+    let value = clean_value(value);
+    if value.is_empty() {
+        return;
+    }
+    match field {
+        Field::Cnpj => {
+            let re = Regex::new(r"\d{2}\.?\d{3}\.?\d{3}/?\d{4}-?\d{2}")
+                .expect("Regex should never fail");
+            if let Some(cnpj) = re.find_iter(&value).find(|m| stands_alone(&value, m)) {
+                record.cnpj = digits_only(cnpj.as_str());
+            }
+            let normalized = normalize(&value);
+            if normalized.contains("MATRIZ") {
+                record.establishment = "MATRIZ".to_string();
+            } else if normalized.contains("FILIAL") {
+                record.establishment = "FILIAL".to_string();
+            }
+        }
+        Field::OpeningDate => record.opening_date = value,
+        Field::CompanyName => record.company_name = value,
+        Field::TradeName => record.trade_name = value,
+        Field::Size => record.size = value,
+        Field::MainActivity => record.main_activity = parse_activity(&value),
+        Field::SecondaryActivities => {
+            if normalize(&value) != "NAO INFORMADA" {
+                record.secondary_activities.push(parse_activity(&value));
+            }
+        }
+        Field::LegalNature => record.legal_nature = parse_activity(&value),
+        Field::Street => record.address.street = value,
+        Field::Number => record.address.number = value,
+        Field::Complement => record.address.complement = value,
+        Field::PostalCode => record.address.postal_code = digits_only(&value),
+        Field::District => record.address.district = value,
+        Field::City => record.address.city = value,
+        Field::State => record.address.state = value,
+        Field::Email => record.email = value,
+        Field::Phone => record.phone = value,
+        Field::Status => record.registration_status = value,
+        Field::StatusDate => record.registration_status_date = value,
+        Field::Ignored => {}
+    }
+}
+
+/// Parses the fixed layout of a transcribed cartão CNPJ into a company record.
+pub fn parse_cartao_cnpj(xml: &str) -> Result<CartaoCnpjData, String> {
+    let mut record = CartaoCnpjData::default();
+    let mut current: Option<Field> = None;
+
+    for line in text_lines(xml)? {
+        if let Some((field, value)) = match_label(&line.text) {
+            if value.is_empty() {
+                current = Some(field);
+            } else {
+                assign(&mut record, field, &value);
+                current = (field == Field::SecondaryActivities).then_some(field);
+            }
+            continue;
+        }
+
+        match current {
+            Some(Field::SecondaryActivities) => {
+                assign(&mut record, Field::SecondaryActivities, &line.text)
+            }
+            Some(field) => {
+                assign(&mut record, field, &line.text);
+                current = None;
+            }
+            None => {}
+        }
+    }
+
+    if !is_valid_cnpj(&record.cnpj) {
+        return Err("Cartão CNPJ does not contain a valid CNPJ".to_string());
+    }
+    if record.company_name.is_empty() {
+        return Err("Cartão CNPJ does not contain the nome empresarial".to_string());
+    }
+    Ok(record)
+}
+
+/// Parses the cartão CNPJ of a document, stores it as its structured data and registers the
+/// company in the entity registry.
+pub fn register_cartao_cnpj(
+    handle: &tauri::AppHandle,
+    document_info: &mut DocumentInfo,
+    xml: &str,
+) -> Result<(), String> {
+    register_company(handle, document_info, parse_cartao_cnpj(xml)?)
+}
+
+/// Stores a company record read from a cartão CNPJ as the structured data of its document and
+/// registers the company in the entity registry, under the digits of its CNPJ.
+pub fn register_company(
+    handle: &tauri::AppHandle,
+    document_info: &mut DocumentInfo,
+    mut record: CartaoCnpjData,
+) -> Result<(), String> {
+    record.cnpj = digits_only(&record.cnpj);
+
+    let mut registry = load_registry(handle)?;
+    registry.upsert_company(&record);
+    save_registry(handle, &registry)?;
+
+    document_info.structured_data = Some(StructuredData::CartaoCnpj(Box::new(record)));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn import_cartao_cnpj(handle: tauri::AppHandle, path: String) -> Result<DocumentInfo, String> {
+    let json_path = Path::new(&path);
+    let mut document_info = read_json_file(json_path)?;
+    let xml = fs::read_to_string(xml_path_for(json_path))
+        .map_err(|e| format!("Failed to read transcription: {}", e))?;
+
+    register_cartao_cnpj(&handle, &mut document_info, &xml)?;
+    document_info.resolved_entities = load_registry(&handle)?.resolve(&document_info);
+
+    let serialized_json = serde_json::to_string(&document_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    save_json_file(&serialized_json, json_path)?;
+    Ok(document_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(lines: &[&str]) -> String {
+        let body: String = lines
+            .iter()
+            .map(|line| format!("<line>{}</line>", line))
+            .collect();
+        format!("<page number=\"1\">{}</page>", body)
+    }
+
+    #[test]
+    fn parses_a_cartao_cnpj() {
+        let xml = card(&[
+            "REPÚBLICA FEDERATIVA DO BRASIL",
+            "CADASTRO NACIONAL DA PESSOA JURÍDICA",
+            "NÚMERO DE INSCRIÇÃO",
+            "11.222.333/0001-81 MATRIZ",
+            "COMPROVANTE DE INSCRIÇÃO E DE SITUAÇÃO CADASTRAL",
+            "DATA DE ABERTURA",
+            "01/02/2010",
+            "NOME EMPRESARIAL",
+            "ACME COMERCIO DE ALIMENTOS LTDA",
+            "TÍTULO DO ESTABELECIMENTO (NOME DE FANTASIA)",
+            "ACME",
+            "PORTE",
+            "ME",
+            "CÓDIGO E DESCRIÇÃO DA ATIVIDADE ECONÔMICA PRINCIPAL",
+            "47.29-6-99 - Comércio varejista de produtos alimentícios em geral",
+            "CÓDIGO E DESCRIÇÃO DAS ATIVIDADES ECONÔMICAS SECUNDÁRIAS",
+            "46.39-7-01 - Comércio atacadista de produtos alimentícios em geral",
+            "56.11-2-03 - Lanchonetes, casas de chá, de sucos e similares",
+            "CÓDIGO E DESCRIÇÃO DA NATUREZA JURÍDICA",
+            "206-2 - Sociedade Empresária Limitada",
+            "LOGRADOURO",
+            "AV PAULISTA",
+            "NÚMERO",
+            "1000",
+            "COMPLEMENTO",
+            "********",
+            "CEP",
+            "01.310-100",
+            "BAIRRO/DISTRITO",
+            "BELA VISTA",
+            "MUNICÍPIO",
+            "SAO PAULO",
+            "UF",
+            "SP",
+            "ENDEREÇO ELETRÔNICO",
+            "CONTATO@ACME.COM.BR",
+            "TELEFONE",
+            "(11) 3333-4444",
+            "ENTE FEDERATIVO RESPONSÁVEL (EFR)",
+            "*****",
+            "SITUAÇÃO CADASTRAL",
+            "ATIVA",
+            "DATA DA SITUAÇÃO CADASTRAL: 01/02/2010",
+        ]);
+
+        let record = parse_cartao_cnpj(&xml).unwrap();
+        assert_eq!(record.cnpj, "11222333000181");
+        assert_eq!(record.establishment, "MATRIZ");
+        assert_eq!(record.opening_date, "01/02/2010");
+        assert_eq!(record.company_name, "ACME COMERCIO DE ALIMENTOS LTDA");
+        assert_eq!(record.trade_name, "ACME");
+        assert_eq!(record.size, "ME");
+        assert_eq!(record.main_activity.code, "47.29-6-99");
+        assert_eq!(
+            record.main_activity.description,
+            "Comércio varejista de produtos alimentícios em geral"
+        );
+        let secondary: Vec<&str> = record
+            .secondary_activities
+            .iter()
+            .map(|activity| activity.code.as_str())
+            .collect();
+        assert_eq!(secondary, ["46.39-7-01", "56.11-2-03"]);
+        assert_eq!(record.legal_nature.code, "206-2");
+        assert_eq!(record.address.street, "AV PAULISTA");
+        assert_eq!(record.address.number, "1000");
+        assert_eq!(record.address.complement, "");
+        assert_eq!(record.address.postal_code, "01310100");
+        assert_eq!(record.address.district, "BELA VISTA");
+        assert_eq!(record.address.city, "SAO PAULO");
+        assert_eq!(record.address.state, "SP");
+        assert_eq!(record.email, "CONTATO@ACME.COM.BR");
+        assert_eq!(record.phone, "(11) 3333-4444");
+        assert_eq!(record.registration_status, "ATIVA");
+        assert_eq!(record.registration_status_date, "01/02/2010");
+    }
+
+    #[test]
+    fn rejects_a_card_with_an_invalid_cnpj() {
+        let xml = card(&[
+            "NÚMERO DE INSCRIÇÃO",
+            "11.222.333/0001-82 MATRIZ",
+            "NOME EMPRESARIAL",
+            "ACME COMERCIO DE ALIMENTOS LTDA",
+        ]);
+
+        assert_eq!(
+            parse_cartao_cnpj(&xml).unwrap_err(),
+            "Cartão CNPJ does not contain a valid CNPJ"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::duplicates::archived_json_path;
+use crate::llm::read_json_file;
+use crate::settings::load_settings;
+
+/// Directory of `done/` where files replaced under the overwrite policy are kept.
+const BACKUP_DIR_NAME: &str = ".backup";
+
+/// What to do when a finished document would take the file name of another one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Refuses to finish or rename the document.
+    Error,
+    /// Adds `-2`, `-3`, ... to the name until it is free.
+    #[default]
+    Suffix,
+    /// Replaces the other file, keeping a copy of it in `done/.backup`.
+    Overwrite,
+}
+
+/// Where a finished document goes in `done/`.
+#[derive(Debug, Clone)]
+pub struct Destination {
+    pub path: PathBuf,
+    pub file_name: String,
+    /// File of another document that has to be backed up before it is replaced.
+    pub replaces: Option<PathBuf>,
+}
+
+pub fn finished_pdf_path(done_dir: &Path, file_name: &str) -> PathBuf {
+    done_dir.join(format!("{}.pdf", file_name))
+}
+
+/// Whether a path is free for a document: nothing is there, or the document's own earlier
+/// version is. Files the archive does not know, as when it was lost or the document was finished
+/// before it existed, are the document's own when its JSON was last finished under that name.
+fn is_free_for(handle: &tauri::AppHandle, path: &Path, json_file_path: &str) -> bool {
+    if !path.exists() {
+        return true;
+    }
+    if let Some(owner) = archived_json_path(handle, path) {
+        return owner == json_file_path;
+    }
+    let Some(done_dir) = path.parent() else {
+        return false;
+    };
+    read_json_file(Path::new(json_file_path))
+        .is_ok_and(|document_info| finished_pdf_path(done_dir, &document_info.file_name) == path)
+}
+
+/// Applies the collision policy to the file name a document should be finished or renamed to.
+pub fn resolve_destination(
+    handle: &tauri::AppHandle,
+    done_dir: &Path,
+    file_name: &str,
+    json_file_path: &str,
+    policy: CollisionPolicy,
+) -> Result<Destination, String> {
+    let path = finished_pdf_path(done_dir, file_name);
+    if is_free_for(handle, &path, json_file_path) {
+        return Ok(Destination {
+            path,
+            file_name: file_name.to_string(),
+            replaces: None,
+        });
+    }
+
+    match policy {
+        CollisionPolicy::Error => Err(format!(
+            "Another document is already finished as {}",
+            path.display()
+        )),
+        CollisionPolicy::Suffix => {
+            let (path, file_name) = (2..)
+                .map(|suffix| {
+                    let file_name = format!("{}-{}", file_name, suffix);
+                    (finished_pdf_path(done_dir, &file_name), file_name)
+                })
+                .find(|(path, _)| is_free_for(handle, path, json_file_path))
+                .expect("Some suffix is always free");
+            Ok(Destination {
+                path,
+                file_name,
+                replaces: None,
+            })
+        }
+        CollisionPolicy::Overwrite => Ok(Destination {
+            replaces: Some(path.clone()),
+            path,
+            file_name: file_name.to_string(),
+        }),
+    }
+}
+
+/// Moves the file of another document out of the way, into `done/.backup`, with the time it was
+/// replaced in its name.
+pub fn back_up(path: &Path) -> Result<PathBuf, String> {
+    let done_dir = path.parent().ok_or("Unable to get parent directory")?;
+    let backup_dir = done_dir.join(BACKUP_DIR_NAME);
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let backup = backup_dir.join(format!(
+        "{}-{}.pdf",
+        stem,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::rename(path, &backup)
+        .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    println!("Backed up {} to {}", path.display(), backup.display());
+    Ok(backup)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollisionPreview {
+    pub policy: CollisionPolicy,
+    pub requested_path: String,
+    /// Whether another document is already finished under the requested name.
+    pub collision: bool,
+    /// Where the document will be written, unless the policy refuses the collision.
+    pub path: Option<String>,
+    pub file_name: Option<String>,
+    /// Whether the other document will be replaced, with a backup.
+    pub overwrites: bool,
+}
+
+/// Tells the UI, before the user commits, whether finishing or renaming a document to a file
+/// name collides with another finished document and what the collision policy will do.
+#[tauri::command]
+pub fn preview_finished_path(
+    handle: tauri::AppHandle,
+    json_file_path: String,
+    file_name: Option<String>,
+) -> Result<CollisionPreview, String> {
+    let policy = load_settings(&handle)?.collision_policy;
+    let json_path = Path::new(&json_file_path);
+    let file_name = match file_name {
+        Some(file_name) => file_name,
+        None => read_json_file(json_path)?.file_name,
+    };
+    let done_dir = json_path
+        .parent()
+        .ok_or("Unable to get parent directory")?
+        .join("done");
+    let requested_path = finished_pdf_path(&done_dir, &file_name);
+    let collision = !is_free_for(&handle, &requested_path, &json_file_path);
+    let destination =
+        resolve_destination(&handle, &done_dir, &file_name, &json_file_path, policy).ok();
+
+    Ok(CollisionPreview {
+        policy,
+        requested_path: requested_path.to_string_lossy().to_string(),
+        collision,
+        overwrites: destination
+            .as_ref()
+            .is_some_and(|destination| destination.replaces.is_some()),
+        path: destination
+            .as_ref()
+            .map(|destination| destination.path.to_string_lossy().to_string()),
+        file_name: destination.map(|destination| destination.file_name),
+    })
+}
@@ -0,0 +1,249 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use tauri::Manager;
+
+use crate::llm::models::DocumentInfo;
+use crate::llm::read_json_file;
+use crate::settings::read_config_file;
+use crate::text::digits_only;
+
+pub mod checklist;
+use checklist::*;
+
+pub mod rules;
+use rules::*;
+
+const DOSSIER_TYPES_FILE_NAME: &str = "dossier_types.json";
+const DEFAULT_DOSSIER_TYPES: &str = include_str!("../resources/dossier_types.json");
+const DOSSIERS_DIR_NAME: &str = "dossiers";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    pub id: String,
+    pub label: String,
+    /// Canonical document types that satisfy the requirement.
+    pub document_types: Vec<String>,
+    #[serde(default)]
+    pub max_age_days: Option<i64>,
+    /// Requires one document per partner listed in the contrato social.
+    #[serde(default)]
+    pub per_partner: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DossierType {
+    pub id: String,
+    pub name: String,
+    pub requirements: Vec<Requirement>,
+}
+
+/// A supplier, customer or employee registration grouping several finished documents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dossier {
+    pub id: String,
+    pub name: String,
+    pub dossier_type: String,
+    #[serde(default)]
+    pub subject_id: Option<String>,
+    /// JSON paths of the documents that belong to the dossier.
+    #[serde(default)]
+    pub documents: Vec<String>,
+    /// Findings of the last consistency check.
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+    #[serde(default)]
+    pub checked_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+pub fn load_dossier_types(handle: &tauri::AppHandle) -> Result<Vec<DossierType>, String> {
+    let content = read_config_file(handle, DOSSIER_TYPES_FILE_NAME, DEFAULT_DOSSIER_TYPES)?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse dossier types: {}", e))
+}
+
+fn dossiers_dir(handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let data_dir = handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(data_dir.join(DOSSIERS_DIR_NAME))
+}
+
+fn dossier_path(handle: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid dossier id: {}", id));
+    }
+    Ok(dossiers_dir(handle)?.join(id).with_extension("json"))
+}
+
+pub fn load_dossier(handle: &tauri::AppHandle, id: &str) -> Result<Dossier, String> {
+    let path = dossier_path(handle, id)?;
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read dossier: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse dossier: {}", e))
+}
+
+pub fn save_dossier(handle: &tauri::AppHandle, dossier: &mut Dossier) -> Result<(), String> {
+    let path = dossier_path(handle, &dossier.id)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    dossier.updated_at = Local::now().to_rfc3339();
+    let content = serde_json::to_string_pretty(dossier)
+        .map_err(|e| format!("Failed to serialize dossier: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write dossier: {}", e))
+}
+
+/// Loads the documents of a dossier, skipping those whose JSON can no longer be read.
+pub fn load_documents(dossier: &Dossier) -> (Vec<DocumentInfo>, Vec<String>) {
+    let mut documents = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in &dossier.documents {
+        match read_json_file(std::path::Path::new(path)) {
+            Ok(document_info) => documents.push(document_info),
+            Err(e) => {
+                println!("Skipping dossier document {}: {}", path, e);
+                unreadable.push(path.clone());
+            }
+        }
+    }
+    (documents, unreadable)
+}
+
+#[tauri::command]
+pub fn get_dossier_types(handle: tauri::AppHandle) -> Result<Vec<DossierType>, String> {
+    load_dossier_types(&handle)
+}
+
+#[tauri::command]
+pub fn create_dossier(
+    handle: tauri::AppHandle,
+    name: String,
+    dossier_type: String,
+    subject_id: Option<String>,
+) -> Result<Dossier, String> {
+    if !load_dossier_types(&handle)?
+        .iter()
+        .any(|existing| existing.id == dossier_type)
+    {
+        return Err(format!("Unknown dossier type: {}", dossier_type));
+    }
+    // CNPJs and CPFs are stored as digits, however they were typed.
+    let subject_id = subject_id
+        .map(|id| digits_only(&id))
+        .filter(|id| !id.is_empty());
+
+    let now = Local::now().to_rfc3339();
+    let mut dossier = Dossier {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        dossier_type,
+        subject_id,
+        documents: Vec::new(),
+        findings: Vec::new(),
+        checked_at: None,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    save_dossier(&handle, &mut dossier)?;
+    Ok(dossier)
+}
+
+#[tauri::command]
+pub fn list_dossiers(handle: tauri::AppHandle) -> Result<Vec<Dossier>, String> {
+    let dir = dossiers_dir(&handle)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut dossiers = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| format!("Failed to read dossiers: {}", e))? {
+        let path = entry
+            .map_err(|e| format!("Failed to read dossiers: {}", e))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read dossier: {}", e))?;
+        let dossier: Dossier = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse dossier: {}", e))?;
+        dossiers.push(dossier);
+    }
+    dossiers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(dossiers)
+}
+
+#[tauri::command]
+pub fn get_dossier(handle: tauri::AppHandle, id: String) -> Result<Dossier, String> {
+    load_dossier(&handle, &id)
+}
+
+#[tauri::command]
+pub fn add_dossier_documents(
+    handle: tauri::AppHandle,
+    id: String,
+    paths: Vec<String>,
+) -> Result<Dossier, String> {
+    let mut dossier = load_dossier(&handle, &id)?;
+    for path in paths {
+        read_json_file(std::path::Path::new(&path))?;
+        if !dossier.documents.contains(&path) {
+            dossier.documents.push(path);
+        }
+    }
+    save_dossier(&handle, &mut dossier)?;
+    Ok(dossier)
+}
+
+#[tauri::command]
+pub fn remove_dossier_document(
+    handle: tauri::AppHandle,
+    id: String,
+    path: String,
+) -> Result<Dossier, String> {
+    let mut dossier = load_dossier(&handle, &id)?;
+    dossier.documents.retain(|existing| existing != &path);
+    save_dossier(&handle, &mut dossier)?;
+    Ok(dossier)
+}
+
+#[tauri::command]
+pub fn delete_dossier(handle: tauri::AppHandle, id: String) -> Result<(), String> {
+    let path = dossier_path(&handle, &id)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete dossier: {}", e))
+}
+
+/// Reports which required documents of a dossier are present, missing or expired.
+#[tauri::command]
+pub fn dossier_checklist(handle: tauri::AppHandle, id: String) -> Result<ChecklistReport, String> {
+    let dossier = load_dossier(&handle, &id)?;
+    let dossier_type = load_dossier_types(&handle)?
+        .into_iter()
+        .find(|existing| existing.id == dossier.dossier_type)
+        .ok_or_else(|| format!("Unknown dossier type: {}", dossier.dossier_type))?;
+
+    let (documents, unreadable) = load_documents(&dossier);
+    Ok(build_checklist(
+        &dossier,
+        &dossier_type,
+        &documents,
+        unreadable,
+        Local::now().date_naive(),
+    ))
+}
+
+/// Runs the consistency rules over the documents of a dossier and stores the findings with it.
+#[tauri::command]
+pub fn check_dossier(handle: tauri::AppHandle, id: String) -> Result<Dossier, String> {
+    let mut dossier = load_dossier(&handle, &id)?;
+    let (documents, _) = load_documents(&dossier);
+
+    dossier.findings = run_rules(&dossier, &documents);
+    dossier.checked_at = Some(Local::now().to_rfc3339());
+    save_dossier(&handle, &mut dossier)?;
+    Ok(dossier)
+}
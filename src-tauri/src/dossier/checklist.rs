@@ -0,0 +1,133 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use super::{Dossier, DossierType, Requirement};
+use crate::extraction::schemas::StructuredData;
+use crate::llm::models::DocumentInfo;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Complete,
+    Partial,
+    Missing,
+    Expired,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChecklistItem {
+    pub requirement_id: String,
+    pub label: String,
+    pub status: ItemStatus,
+    pub required_count: usize,
+    /// JSON paths of the valid documents that satisfy the requirement.
+    pub documents: Vec<String>,
+    /// JSON paths of the matching documents that are past their validity.
+    pub expired_documents: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChecklistReport {
+    pub dossier_id: String,
+    pub complete: bool,
+    pub items: Vec<ChecklistItem>,
+    pub unreadable_documents: Vec<String>,
+}
+
+fn is_expired(document_info: &DocumentInfo, requirement: &Requirement, today: NaiveDate) -> bool {
+    if let Some(validity) = &document_info.validity {
+        if validity.expiry_date < today {
+            return true;
+        }
+    }
+
+    let Some(max_age_days) = requirement.max_age_days else {
+        return false;
+    };
+    let issue_date = document_info
+        .validity
+        .as_ref()
+        .and_then(|validity| validity.issue_date)
+        .or_else(|| document_info.reasoning.important_date.parse());
+    issue_date.is_some_and(|date| date + Duration::days(max_age_days) < today)
+}
+
+fn partner_count(documents: &[DocumentInfo]) -> usize {
+    documents
+        .iter()
+        .filter_map(|document_info| match &document_info.structured_data {
+            Some(StructuredData::ContratoSocial(data)) => Some(data.partners.len()),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn check_requirement(
+    requirement: &Requirement,
+    documents: &[DocumentInfo],
+    partners: usize,
+    today: NaiveDate,
+) -> ChecklistItem {
+    let (expired, valid): (Vec<&DocumentInfo>, Vec<&DocumentInfo>) = documents
+        .iter()
+        .filter(|document_info| {
+            document_info
+                .canonical_type
+                .as_ref()
+                .is_some_and(|canonical| requirement.document_types.contains(&canonical.id))
+        })
+        .partition(|document_info| is_expired(document_info, requirement, today));
+
+    let required_count = if requirement.per_partner {
+        partners.max(1)
+    } else {
+        1
+    };
+    let status = if valid.len() >= required_count {
+        ItemStatus::Complete
+    } else if !valid.is_empty() {
+        ItemStatus::Partial
+    } else if !expired.is_empty() {
+        ItemStatus::Expired
+    } else {
+        ItemStatus::Missing
+    };
+
+    ChecklistItem {
+        requirement_id: requirement.id.clone(),
+        label: requirement.label.clone(),
+        status,
+        required_count,
+        documents: valid
+            .iter()
+            .map(|document_info| document_info.json_file_path.clone())
+            .collect(),
+        expired_documents: expired
+            .iter()
+            .map(|document_info| document_info.json_file_path.clone())
+            .collect(),
+    }
+}
+
+pub fn build_checklist(
+    dossier: &Dossier,
+    dossier_type: &DossierType,
+    documents: &[DocumentInfo],
+    unreadable_documents: Vec<String>,
+    today: NaiveDate,
+) -> ChecklistReport {
+    let partners = partner_count(documents);
+    let items: Vec<ChecklistItem> = dossier_type
+        .requirements
+        .iter()
+        .map(|requirement| check_requirement(requirement, documents, partners, today))
+        .collect();
+
+    ChecklistReport {
+        dossier_id: dossier.id.clone(),
+        complete: items.iter().all(|item| item.status == ItemStatus::Complete),
+        items,
+        unreadable_documents,
+    }
+}
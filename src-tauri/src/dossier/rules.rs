@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+
+use super::Dossier;
+use crate::entities::{is_valid_cnpj, name_similarity};
+use crate::extraction::schemas::{Address, IdentidadeData, Partner, StructuredData};
+use crate::llm::models::DocumentInfo;
+use crate::text::{digits_only, similarity};
+
+const NAME_THRESHOLD: f64 = 0.9;
+const STREET_THRESHOLD: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    /// JSON paths of the documents involved in the finding.
+    pub documents: Vec<String>,
+}
+
+/// A consistency check over the structured data of the documents of a dossier.
+pub trait Rule {
+    fn id(&self) -> &'static str;
+    fn check(&self, dossier: &Dossier, documents: &[DocumentInfo]) -> Vec<Finding>;
+
+    fn finding(&self, severity: Severity, message: String, documents: &[&DocumentInfo]) -> Finding {
+        Finding {
+            rule: self.id().to_string(),
+            severity,
+            message,
+            documents: documents
+                .iter()
+                .map(|document_info| document_info.json_file_path.clone())
+                .collect(),
+        }
+    }
+}
+
+pub const RULES: &[&dyn Rule] = &[&CnpjConsistency, &AddressConsistency, &PartnerIdentity];
+
+pub fn run_rules(dossier: &Dossier, documents: &[DocumentInfo]) -> Vec<Finding> {
+    RULES
+        .iter()
+        .flat_map(|rule| rule.check(dossier, documents))
+        .collect()
+}
+
+fn with_data<'a, T>(
+    documents: &'a [DocumentInfo],
+    select: impl Fn(&'a StructuredData) -> Option<&'a T>,
+) -> Vec<(&'a DocumentInfo, &'a T)> {
+    documents
+        .iter()
+        .filter_map(|document_info| {
+            document_info
+                .structured_data
+                .as_ref()
+                .and_then(&select)
+                .map(|data| (document_info, data))
+        })
+        .collect()
+}
+
+/// The CNPJ of the cartão CNPJ must match the dossier subject, the contrato social and appear as
+/// emitter or recipient of every NF-e.
+pub struct CnpjConsistency;
+
+impl Rule for CnpjConsistency {
+    fn id(&self) -> &'static str {
+        "cnpj_consistency"
+    }
+
+    fn check(&self, dossier: &Dossier, documents: &[DocumentInfo]) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let cartoes = with_data(documents, |data| match data {
+            StructuredData::CartaoCnpj(data) => Some(data),
+            _ => None,
+        });
+
+        for (document_info, data) in &cartoes {
+            if !is_valid_cnpj(&data.cnpj) {
+                findings.push(self.finding(
+                    Severity::Error,
+                    format!("Invalid CNPJ on the cartão CNPJ: {}", data.cnpj),
+                    &[document_info],
+                ));
+            }
+        }
+
+        let subject = dossier
+            .subject_id
+            .as_deref()
+            .map(digits_only)
+            .filter(|id| is_valid_cnpj(id))
+            .or_else(|| {
+                cartoes
+                    .iter()
+                    .map(|(_, data)| digits_only(&data.cnpj))
+                    .find(|cnpj| is_valid_cnpj(cnpj))
+            });
+        let Some(subject) = subject else {
+            findings.push(self.finding(
+                Severity::Info,
+                "No CNPJ to compare: the dossier has no subject and no cartão CNPJ".to_string(),
+                &[],
+            ));
+            return findings;
+        };
+
+        for (document_info, data) in &cartoes {
+            if digits_only(&data.cnpj) != subject {
+                findings.push(self.finding(
+                    Severity::Error,
+                    format!(
+                        "CNPJ of the cartão CNPJ ({}) differs from the dossier ({})",
+                        data.cnpj, subject
+                    ),
+                    &[document_info],
+                ));
+            }
+        }
+
+        let contratos = with_data(documents, |data| match data {
+            StructuredData::ContratoSocial(data) => Some(data),
+            _ => None,
+        });
+        for (document_info, data) in contratos {
+            if !data.cnpj.is_empty() && digits_only(&data.cnpj) != subject {
+                findings.push(self.finding(
+                    Severity::Error,
+                    format!(
+                        "CNPJ of the contrato social ({}) differs from the dossier ({})",
+                        data.cnpj, subject
+                    ),
+                    &[document_info],
+                ));
+            }
+        }
+
+        let invoices = with_data(documents, |data| match data {
+            StructuredData::Nfe(data) => Some(data),
+            _ => None,
+        });
+        for (document_info, data) in invoices {
+            let parties = [&data.emitter.document, &data.recipient.document];
+            if !parties
+                .iter()
+                .any(|document| digits_only(document) == subject)
+            {
+                findings.push(self.finding(
+                    Severity::Error,
+                    format!(
+                        "NF-e {} was neither issued by nor addressed to the dossier CNPJ ({})",
+                        data.number, subject
+                    ),
+                    &[document_info],
+                ));
+            }
+        }
+
+        findings
+    }
+}
+
+fn addresses_match(a: &Address, b: &Address) -> bool {
+    let (a_cep, b_cep) = (digits_only(&a.postal_code), digits_only(&b.postal_code));
+    if !a_cep.is_empty() && !b_cep.is_empty() && a_cep != b_cep {
+        return false;
+    }
+    let (a_number, b_number) = (digits_only(&a.number), digits_only(&b.number));
+    if !a_number.is_empty() && !b_number.is_empty() && a_number != b_number {
+        return false;
+    }
+    similarity(&a.street, &b.street) >= STREET_THRESHOLD
+        || name_similarity(&a.street, &b.street) >= STREET_THRESHOLD
+}
+
+fn format_address(address: &Address) -> String {
+    [
+        &address.street,
+        &address.number,
+        &address.city,
+        &address.postal_code,
+    ]
+    .iter()
+    .filter(|part| !part.is_empty())
+    .map(|part| part.as_str())
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The address on every comprovante de endereço must match the registered address, taken from
+/// the cartão CNPJ or, failing that, from the contrato social.
+pub struct AddressConsistency;
+
+impl Rule for AddressConsistency {
+    fn id(&self) -> &'static str {
+        "address_consistency"
+    }
+
+    fn check(&self, _dossier: &Dossier, documents: &[DocumentInfo]) -> Vec<Finding> {
+        let registered = with_data(documents, |data| match data {
+            StructuredData::CartaoCnpj(data) => Some(&data.address),
+            _ => None,
+        })
+        .into_iter()
+        .chain(with_data(documents, |data| match data {
+            StructuredData::ContratoSocial(data) => Some(&data.address),
+            _ => None,
+        }))
+        .find(|(_, address)| !address.street.is_empty());
+
+        let comprovantes = with_data(documents, |data| match data {
+            StructuredData::ComprovanteResidencia(data) => Some(&data.address),
+            _ => None,
+        });
+        if comprovantes.is_empty() {
+            return Vec::new();
+        }
+
+        let Some((registered_document, registered_address)) = registered else {
+            return vec![self.finding(
+                Severity::Info,
+                "No registered address to compare the comprovantes de endereço with".to_string(),
+                &[],
+            )];
+        };
+
+        comprovantes
+            .into_iter()
+            .filter(|(_, address)| !addresses_match(address, registered_address))
+            .map(|(document_info, address)| {
+                self.finding(
+                    Severity::Warning,
+                    format!(
+                        "Address on the comprovante ({}) differs from the registered address ({})",
+                        format_address(address),
+                        format_address(registered_address)
+                    ),
+                    &[document_info, registered_document],
+                )
+            })
+            .collect()
+    }
+}
+
+fn same_person(partner: &Partner, holder: &IdentidadeData) -> (bool, bool) {
+    let (partner_cpf, holder_cpf) = (digits_only(&partner.cpf), digits_only(&holder.cpf));
+    let cpf_matches = !partner_cpf.is_empty() && partner_cpf == holder_cpf;
+    let name_matches = name_similarity(&partner.name, &holder.name) >= NAME_THRESHOLD;
+    (cpf_matches, name_matches)
+}
+
+/// The partners listed in the contrato social must match the holders of the RG/CNH documents.
+pub struct PartnerIdentity;
+
+impl Rule for PartnerIdentity {
+    fn id(&self) -> &'static str {
+        "partner_identity"
+    }
+
+    fn check(&self, _dossier: &Dossier, documents: &[DocumentInfo]) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let holders = with_data(documents, |data| match data {
+            StructuredData::Identidade(data) => Some(data),
+            _ => None,
+        });
+        let contratos = with_data(documents, |data| match data {
+            StructuredData::ContratoSocial(data) => Some(data),
+            _ => None,
+        });
+        let Some((contrato, data)) = contratos.first() else {
+            return findings;
+        };
+
+        let mut matched_holders = Vec::new();
+        for partner in &data.partners {
+            let found = holders
+                .iter()
+                .enumerate()
+                .find_map(|(index, (doc, holder))| {
+                    let (cpf_matches, name_matches) = same_person(partner, holder);
+                    (cpf_matches || name_matches).then_some((index, doc, holder, name_matches))
+                });
+
+            match found {
+                Some((index, doc, holder, name_matches)) => {
+                    matched_holders.push(index);
+                    if !name_matches {
+                        findings.push(self.finding(
+                            Severity::Error,
+                            format!(
+                                "Partner {} shares the CPF {} with {}, but the names differ",
+                                partner.name, partner.cpf, holder.name
+                            ),
+                            &[contrato, doc],
+                        ));
+                    }
+                }
+                None => findings.push(self.finding(
+                    Severity::Warning,
+                    format!("No RG/CNH found for partner {}", partner.name),
+                    &[contrato],
+                )),
+            }
+        }
+
+        for (index, (doc, holder)) in holders.iter().enumerate() {
+            if !matched_holders.contains(&index) {
+                findings.push(self.finding(
+                    Severity::Warning,
+                    format!(
+                        "{} is not listed as a partner in the contrato social",
+                        holder.name
+                    ),
+                    &[doc, contrato],
+                ));
+            }
+        }
+
+        findings
+    }
+}
@@ -0,0 +1,310 @@
+use chrono::Local;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri::{async_runtime, Manager};
+
+use crate::llm::models::DocumentInfo;
+use crate::text::normalize;
+use crate::transcription::{text_lines, xml_path_for};
+use crate::workspace::{load_workspace, page_number, save_workspace, Workspace};
+
+const ARCHIVE_FILE_NAME: &str = "archive.json";
+/// Page hashes this many bits apart or less are the same page scanned twice.
+const MAX_HASH_DISTANCE: u32 = 6;
+
+/// Difference hash of an image: one bit per horizontally adjacent pair of a 9x8 thumbnail.
+pub fn dhash(image: &image::DynamicImage) -> u64 {
+    let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumbnail.get_pixel(x, y).0[0] < thumbnail.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hash of the normalized text of a transcription, so that whitespace and accents do not count.
+pub fn content_hash(xml: &str) -> Result<String, String> {
+    let text = text_lines(xml)?
+        .iter()
+        .map(|line| normalize(&line.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(format!("{:x}", Sha256::digest(text.as_bytes())))
+}
+
+/// Perceptual hashes of page images, cached in the workspace of their scan. Pages the workspace
+/// leaves out of the document, as blank or separator pages, have none.
+fn page_hashes(paths: &[String]) -> Result<Vec<Option<u64>>, String> {
+    let mut workspaces: HashMap<PathBuf, Workspace> = HashMap::new();
+    let mut hashes = Vec::new();
+
+    for path in paths {
+        let image_path = Path::new(path);
+        let data_dir = image_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let page = page_number(path);
+        if !workspaces.contains_key(&data_dir) {
+            workspaces.insert(data_dir.clone(), load_workspace(&data_dir)?);
+        }
+        let workspace = workspaces
+            .get_mut(&data_dir)
+            .expect("Workspace was just loaded");
+        if page.is_some_and(|page| workspace.is_skipped(page)) {
+            hashes.push(None);
+            continue;
+        }
+
+        let cached = page
+            .and_then(|page| workspace.pages.get(&page))
+            .and_then(|state| state.dhash.as_deref())
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok());
+        let hash = match cached {
+            Some(hash) => hash,
+            None => {
+                let image = image::open(image_path)
+                    .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+                let hash = dhash(&image);
+                if let Some(page) = page {
+                    let state = workspace.pages.entry(page).or_default();
+                    state.dhash = Some(format!("{:016x}", hash));
+                }
+                hash
+            }
+        };
+        hashes.push(Some(hash));
+    }
+
+    for (data_dir, workspace) in &workspaces {
+        save_workspace(data_dir, workspace)?;
+    }
+    Ok(hashes)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePage {
+    pub path: String,
+    pub group: usize,
+    pub duplicate_path: String,
+    pub duplicate_group: usize,
+    pub distance: u32,
+}
+
+fn duplicate_pages(groups: &[Vec<String>]) -> Result<Vec<DuplicatePage>, String> {
+    let mut pages = Vec::new();
+    for (group, paths) in groups.iter().enumerate() {
+        for (path, hash) in paths.iter().zip(page_hashes(paths)?) {
+            if let Some(hash) = hash {
+                pages.push((group, path, hash));
+            }
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for (index, (group, path, hash)) in pages.iter().enumerate() {
+        for (other_group, other_path, other_hash) in &pages[index + 1..] {
+            let distance = hash_distance(*hash, *other_hash);
+            if group != other_group && distance <= MAX_HASH_DISTANCE {
+                duplicates.push(DuplicatePage {
+                    path: other_path.to_string(),
+                    group: *other_group,
+                    duplicate_path: path.to_string(),
+                    duplicate_group: *group,
+                    distance,
+                });
+            }
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Warns about pages that appear in more than one of the given page groups, such as an invoice
+/// that was fed twice through the scanner.
+#[tauri::command]
+pub async fn check_duplicate_pages(groups: Vec<Vec<String>>) -> Result<Vec<DuplicatePage>, String> {
+    async_runtime::spawn_blocking(move || duplicate_pages(&groups))
+        .await
+        .map_err(|e| format!("Failed to compare pages: {}", e))?
+}
+
+/// A finished document, as recorded when `final_pipeline` moved it into `done/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedDocument {
+    pub file_name: String,
+    pub pdf_path: String,
+    pub json_file_path: String,
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    #[serde(default)]
+    pub page_hashes: Vec<String>,
+    pub archived_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Archive {
+    pub documents: Vec<ArchivedDocument>,
+}
+
+fn archive_path(handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let data_dir = handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(data_dir.join(ARCHIVE_FILE_NAME))
+}
+
+fn load_archive(handle: &tauri::AppHandle) -> Result<Archive, String> {
+    let path = archive_path(handle)?;
+    if !path.exists() {
+        return Ok(Archive::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read archive: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse archive: {}", e))
+}
+
+fn save_archive(handle: &tauri::AppHandle, archive: &Archive) -> Result<(), String> {
+    let path = archive_path(handle)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(archive)
+        .map_err(|e| format!("Failed to serialize archive: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write archive: {}", e))
+}
+
+/// Content hash of a document, computed from its cached transcription when it predates the field.
+fn document_content_hash(document_info: &DocumentInfo) -> Option<String> {
+    document_info.content_hash.clone().or_else(|| {
+        fs::read_to_string(xml_path_for(Path::new(&document_info.json_file_path)))
+            .ok()
+            .and_then(|xml| content_hash(&xml).ok())
+    })
+}
+
+fn same_pages(hashes: &[u64], archived: &[String]) -> bool {
+    !hashes.is_empty()
+        && hashes.len() == archived.len()
+        && hashes.iter().zip(archived).all(|(hash, archived)| {
+            u64::from_str_radix(archived, 16)
+                .is_ok_and(|archived| hash_distance(*hash, archived) <= MAX_HASH_DISTANCE)
+        })
+}
+
+fn find_archived(
+    archive: &Archive,
+    document_info: &DocumentInfo,
+    content_hash: Option<&str>,
+    hashes: &[u64],
+) -> Option<ArchivedDocument> {
+    archive
+        .documents
+        .iter()
+        .filter(|archived| archived.json_file_path != document_info.json_file_path)
+        .filter(|archived| Path::new(&archived.pdf_path).exists())
+        .find(|archived| {
+            (content_hash.is_some() && archived.content_hash.as_deref() == content_hash)
+                || same_pages(hashes, &archived.page_hashes)
+        })
+        .cloned()
+}
+
+/// Looks for an already finished document with the same content or the same page images.
+#[tauri::command]
+pub async fn check_archive_duplicate(
+    handle: tauri::AppHandle,
+    document_info: DocumentInfo,
+) -> Result<Option<ArchivedDocument>, String> {
+    let archive = load_archive(&handle)?;
+    let content_hash = document_content_hash(&document_info);
+    let paths = document_info.pages_paths.clone();
+    let hashes: Vec<u64> = async_runtime::spawn_blocking(move || page_hashes(&paths))
+        .await
+        .map_err(|e| format!("Failed to hash pages: {}", e))??
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(find_archived(
+        &archive,
+        &document_info,
+        content_hash.as_deref(),
+        &hashes,
+    ))
+}
+
+/// Records a finished document in the archive, replacing an earlier entry for the same JSON.
+pub async fn record_archived(
+    handle: &tauri::AppHandle,
+    document_info: &DocumentInfo,
+    pdf_path: &Path,
+) -> Result<(), String> {
+    let paths = document_info.pages_paths.clone();
+    let page_hashes = async_runtime::spawn_blocking(move || page_hashes(&paths))
+        .await
+        .map_err(|e| format!("Failed to hash pages: {}", e))??
+        .into_iter()
+        .flatten()
+        .map(|hash| format!("{:016x}", hash))
+        .collect();
+
+    let mut archive = load_archive(handle)?;
+
+    archive
+        .documents
+        .retain(|archived| archived.json_file_path != document_info.json_file_path);
+    archive.documents.push(ArchivedDocument {
+        file_name: document_info.file_name.clone(),
+        pdf_path: pdf_path.to_string_lossy().to_string(),
+        json_file_path: document_info.json_file_path.clone(),
+        content_hash: document_content_hash(document_info),
+        page_hashes,
+        archived_at: Local::now().to_rfc3339(),
+    });
+    save_archive(handle, &archive)
+}
+
+/// JSON of the document an archived PDF belongs to.
+pub fn archived_json_path(handle: &tauri::AppHandle, pdf_path: &Path) -> Option<String> {
+    load_archive(handle)
+        .ok()?
+        .documents
+        .into_iter()
+        .find(|archived| Path::new(&archived.pdf_path) == pdf_path)
+        .map(|archived| archived.json_file_path)
+}
+
+/// Follows a finished document that was renamed, so the archive keeps pointing at its PDF.
+pub fn update_archived_path(
+    handle: &tauri::AppHandle,
+    document_info: &DocumentInfo,
+    pdf_path: &Path,
+) -> Result<(), String> {
+    let mut archive = load_archive(handle)?;
+    let Some(archived) = archive
+        .documents
+        .iter_mut()
+        .find(|archived| archived.json_file_path == document_info.json_file_path)
+    else {
+        return Ok(());
+    };
+    archived.file_name = document_info.file_name.clone();
+    archived.pdf_path = pdf_path.to_string_lossy().to_string();
+    save_archive(handle, &archive)
+}
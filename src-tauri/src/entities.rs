@@ -0,0 +1,493 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use tauri::Manager;
+
+use crate::extraction::schemas::CartaoCnpjData;
+use crate::llm::models::{DocumentInfo, ResolvedEntity};
+use crate::llm::{read_json_file, save_json_file};
+use crate::text::{digits_only, normalize, similarity, stands_alone};
+
+const REGISTRY_FILE_NAME: &str = "entities.json";
+const MATCH_THRESHOLD: f64 = 0.8;
+const LEGAL_SUFFIXES: &[&str] = &["LTDA", "ME", "EPP", "EIRELI", "SA", "S A", "SS", "MEI"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Company,
+    Person,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    /// CNPJ or CPF, digits only.
+    pub id: String,
+    pub kind: EntityKind,
+    pub canonical_name: String,
+    pub short_name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Company record taken from the cartão CNPJ, when one was imported.
+    #[serde(default)]
+    pub company: Option<CartaoCnpjData>,
+}
+
+impl Entity {
+    fn names(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.canonical_name)
+            .chain(std::iter::once(&self.short_name))
+            .chain(self.aliases.iter())
+    }
+
+    fn resolved(&self, matched: &str, score: f64) -> ResolvedEntity {
+        ResolvedEntity {
+            id: self.id.clone(),
+            canonical_name: self.canonical_name.clone(),
+            short_name: self.short_name.clone(),
+            matched: matched.to_string(),
+            score,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EntityRegistry {
+    pub entities: Vec<Entity>,
+}
+
+impl EntityRegistry {
+    pub fn get(&self, id: &str) -> Option<&Entity> {
+        self.entities.iter().find(|entity| entity.id == id)
+    }
+
+    /// Creates or updates the company of a cartão CNPJ, keeping previous names as aliases.
+    pub fn upsert_company(&mut self, company: &CartaoCnpjData) {
+        let short_name = if company.trade_name.is_empty() {
+            name_tokens(&company.company_name).join(" ")
+        } else {
+            company.trade_name.clone()
+        };
+
+        let existing = self
+            .entities
+            .iter_mut()
+            .find(|entity| entity.id == company.cnpj);
+        let Some(entity) = existing else {
+            self.entities.push(Entity {
+                id: company.cnpj.clone(),
+                kind: EntityKind::Company,
+                canonical_name: company.company_name.clone(),
+                short_name,
+                aliases: Vec::new(),
+                company: Some(company.clone()),
+            });
+            return;
+        };
+
+        if normalize(&entity.canonical_name) != normalize(&company.company_name) {
+            let previous =
+                std::mem::replace(&mut entity.canonical_name, company.company_name.clone());
+            entity.aliases.push(previous);
+        }
+        if entity.short_name.is_empty() {
+            entity.short_name = short_name;
+        }
+        entity.kind = EntityKind::Company;
+        entity.company = Some(company.clone());
+    }
+
+    /// Replaces the entity with the given CNPJ/CPF, which may itself change.
+    pub fn edit(&mut self, id: &str, entity: Entity) -> Result<Entity, String> {
+        let entity = validate_entity(entity)?;
+        let id = digits_only(id);
+        if entity.id != id && self.get(&entity.id).is_some() {
+            return Err(format!("Entity already exists: {}", entity.id));
+        }
+
+        let existing = self
+            .entities
+            .iter_mut()
+            .find(|existing| existing.id == id)
+            .ok_or_else(|| format!("Entity not found: {}", id))?;
+        *existing = entity.clone();
+        Ok(entity)
+    }
+
+    /// Folds the source entities into the target. Every id has to be in the registry.
+    pub fn merge(&mut self, target_id: &str, source_ids: &[String]) -> Result<Entity, String> {
+        let target_id = digits_only(target_id);
+        let source_ids: Vec<String> = source_ids
+            .iter()
+            .map(|id| digits_only(id))
+            .filter(|id| *id != target_id)
+            .collect();
+        for id in std::iter::once(&target_id).chain(&source_ids) {
+            if self.get(id).is_none() {
+                return Err(format!("Entity not found: {}", id));
+            }
+        }
+
+        let (sources, mut entities): (Vec<Entity>, Vec<Entity>) =
+            std::mem::take(&mut self.entities)
+                .into_iter()
+                .partition(|entity| source_ids.contains(&entity.id));
+        let target = entities
+            .iter_mut()
+            .find(|entity| entity.id == target_id)
+            .expect("Target entity was checked above");
+
+        for name in sources.iter().flat_map(|source| source.names()) {
+            let is_known = target
+                .names()
+                .any(|existing| normalize(existing) == normalize(name));
+            if !is_known {
+                target.aliases.push(name.clone());
+            }
+        }
+
+        let merged = target.clone();
+        self.entities = entities;
+        Ok(merged)
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<(&Entity, f64)> {
+        self.entities
+            .iter()
+            .map(|entity| {
+                let score = entity
+                    .names()
+                    .map(|candidate| name_similarity(candidate, name))
+                    .fold(0.0, f64::max);
+                (entity, score)
+            })
+            .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Resolves the entities extracted from a document, first by CNPJ/CPF and then by name.
+    pub fn resolve(&self, document_info: &DocumentInfo) -> Vec<ResolvedEntity> {
+        let reasoning = &document_info.reasoning;
+        let mut resolved: Vec<ResolvedEntity> = Vec::new();
+        let mut push = |entity: ResolvedEntity| {
+            if !resolved.iter().any(|existing| existing.id == entity.id) {
+                resolved.push(entity);
+            }
+        };
+
+        for name in split_entity_names(&reasoning.main_entities.entities) {
+            let by_id = find_identifiers(&name)
+                .into_iter()
+                .find_map(|id| self.get(&id))
+                .map(|entity| entity.resolved(&name, 1.0));
+            let by_name = || {
+                self.find_by_name(&name)
+                    .map(|(entity, score)| entity.resolved(&name, score))
+            };
+            if let Some(entity) = by_id.or_else(by_name) {
+                push(entity);
+            }
+        }
+
+        for identifier in [&reasoning.identifiers.cnpj, &reasoning.identifiers.cpf] {
+            if let Some(entity) = self.get(&digits_only(identifier)) {
+                push(entity.resolved(identifier, 1.0));
+            }
+        }
+
+        resolved
+    }
+}
+
+pub fn split_entity_names(entities: &str) -> Vec<String> {
+    entities
+        .split([';', '\n'])
+        .map(|entity| entity.trim())
+        .filter(|entity| !entity.is_empty())
+        .map(|entity| entity.to_string())
+        .collect()
+}
+
+fn name_tokens(name: &str) -> Vec<String> {
+    let mut normalized = format!(" {} ", normalize(name));
+    for suffix in LEGAL_SUFFIXES {
+        normalized = normalized.replace(&format!(" {} ", suffix), " ");
+    }
+    normalized
+        .split_whitespace()
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// Compares company names token by token, treating abbreviated tokens ("TEC") as matching
+/// their full form ("TECNOLOGIA") and ignoring legal suffixes such as LTDA or ME.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a_tokens, b_tokens) = (name_tokens(a), name_tokens(b));
+    if a_tokens.is_empty() || b_tokens.is_empty() {
+        return similarity(a, b);
+    }
+
+    let mut remaining = b_tokens.clone();
+    let mut matched = 0;
+    for token in &a_tokens {
+        let position = remaining.iter().position(|other| {
+            other == token
+                || (token.len() >= 3 && other.starts_with(token.as_str()))
+                || (other.len() >= 3 && token.starts_with(other.as_str()))
+        });
+        if let Some(position) = position {
+            remaining.remove(position);
+            matched += 1;
+        }
+    }
+
+    let token_score = (2 * matched) as f64 / (a_tokens.len() + b_tokens.len()) as f64;
+    token_score.max(similarity(&a_tokens.join(" "), &b_tokens.join(" ")))
+}
+
+fn check_digit(digits: &[u32], weights: &[u32]) -> u32 {
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+    match sum % 11 {
+        0 | 1 => 0,
+        rest => 11 - rest,
+    }
+}
+
+pub fn is_valid_cnpj(value: &str) -> bool {
+    let digits: Vec<u32> = digits_only(value)
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect();
+    if digits.len() != 14 || digits.iter().all(|d| *d == digits[0]) {
+        return false;
+    }
+    let first = check_digit(&digits[..12], &[5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]);
+    let second = check_digit(&digits[..13], &[6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]);
+    digits[12] == first && digits[13] == second
+}
+
+pub fn is_valid_cpf(value: &str) -> bool {
+    let digits: Vec<u32> = digits_only(value)
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect();
+    if digits.len() != 11 || digits.iter().all(|d| *d == digits[0]) {
+        return false;
+    }
+    let first = check_digit(&digits[..9], &[10, 9, 8, 7, 6, 5, 4, 3, 2]);
+    let second = check_digit(&digits[..10], &[11, 10, 9, 8, 7, 6, 5, 4, 3, 2]);
+    digits[9] == first && digits[10] == second
+}
+
+/// Finds valid CNPJs and CPFs in free text, returned as digits only. Digits inside a longer
+/// number are not taken for one.
+pub fn find_identifiers(text: &str) -> Vec<String> {
+    let re = Regex::new(r"\d{2}\.?\d{3}\.?\d{3}/?\d{4}-?\d{2}|\d{3}\.?\d{3}\.?\d{3}-?\d{2}")
+        .expect("Regex should never fail");
+    re.find_iter(text)
+        .filter(|m| stands_alone(text, m))
+        .map(|m| digits_only(m.as_str()))
+        .filter(|id| is_valid_cnpj(id) || is_valid_cpf(id))
+        .collect()
+}
+
+fn registry_path(handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let data_dir = handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(data_dir.join(REGISTRY_FILE_NAME))
+}
+
+pub fn load_registry(handle: &tauri::AppHandle) -> Result<EntityRegistry, String> {
+    let path = registry_path(handle)?;
+    if !path.exists() {
+        return Ok(EntityRegistry::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read entity registry: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse entity registry: {}", e))
+}
+
+pub fn save_registry(handle: &tauri::AppHandle, registry: &EntityRegistry) -> Result<(), String> {
+    let path = registry_path(handle)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(registry)
+        .map_err(|e| format!("Failed to serialize entity registry: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write entity registry: {}", e))
+}
+
+fn validate_entity(mut entity: Entity) -> Result<Entity, String> {
+    entity.id = digits_only(&entity.id);
+    let valid = match entity.kind {
+        EntityKind::Company => is_valid_cnpj(&entity.id),
+        EntityKind::Person => is_valid_cpf(&entity.id),
+    };
+    if !valid {
+        return Err(format!("Invalid CNPJ/CPF: {}", entity.id));
+    }
+    if entity.canonical_name.trim().is_empty() {
+        return Err("Entity canonical name is empty".to_string());
+    }
+    if entity.short_name.trim().is_empty() {
+        entity.short_name = entity.canonical_name.clone();
+    }
+    Ok(entity)
+}
+
+#[tauri::command]
+pub fn list_entities(handle: tauri::AppHandle) -> Result<Vec<Entity>, String> {
+    Ok(load_registry(&handle)?.entities)
+}
+
+#[tauri::command]
+pub fn add_entity(handle: tauri::AppHandle, entity: Entity) -> Result<Entity, String> {
+    let entity = validate_entity(entity)?;
+    let mut registry = load_registry(&handle)?;
+    if registry.get(&entity.id).is_some() {
+        return Err(format!("Entity already exists: {}", entity.id));
+    }
+
+    registry.entities.push(entity.clone());
+    save_registry(&handle, &registry)?;
+    Ok(entity)
+}
+
+#[tauri::command]
+pub fn edit_entity(handle: tauri::AppHandle, id: String, entity: Entity) -> Result<Entity, String> {
+    let mut registry = load_registry(&handle)?;
+    let entity = registry.edit(&id, entity)?;
+    save_registry(&handle, &registry)?;
+    Ok(entity)
+}
+
+/// Merges the source entities into the target, keeping their names as aliases of the target.
+#[tauri::command]
+pub fn merge_entities(
+    handle: tauri::AppHandle,
+    target_id: String,
+    source_ids: Vec<String>,
+) -> Result<Entity, String> {
+    let mut registry = load_registry(&handle)?;
+    let merged = registry.merge(&target_id, &source_ids)?;
+    save_registry(&handle, &registry)?;
+    Ok(merged)
+}
+
+/// Resolves the entities of an existing document against the current registry.
+#[tauri::command]
+pub fn resolve_document_entities(
+    handle: tauri::AppHandle,
+    path: String,
+) -> Result<DocumentInfo, String> {
+    let registry = load_registry(&handle)?;
+    let json_path = std::path::Path::new(&path);
+    let mut document_info = read_json_file(json_path)?;
+    document_info.resolved_entities = registry.resolve(&document_info);
+
+    let serialized_json = serde_json::to_string(&document_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    save_json_file(&serialized_json, json_path)?;
+    Ok(document_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_identifiers_standing_on_their_own() {
+        assert_eq!(
+            find_identifiers("CNPJ 11.222.333/0001-81, CPF 529.982.247-25 e 11222333000181."),
+            vec!["11222333000181", "52998224725", "11222333000181"]
+        );
+    }
+
+    #[test]
+    fn ignores_identifiers_inside_longer_numbers() {
+        // An access key of an NF-e holds the CNPJ of the emitter.
+        let access_key = "35240311222333000181550010000001231000001234";
+        assert!(find_identifiers(access_key).is_empty());
+        assert!(find_identifiers("Código 9529982247251").is_empty());
+        assert!(find_identifiers("011.222.333/0001-81").is_empty());
+    }
+
+    fn company(id: &str, name: &str) -> Entity {
+        Entity {
+            id: id.to_string(),
+            kind: EntityKind::Company,
+            canonical_name: name.to_string(),
+            short_name: name.to_string(),
+            aliases: Vec::new(),
+            company: None,
+        }
+    }
+
+    fn registry() -> EntityRegistry {
+        EntityRegistry {
+            entities: vec![
+                company("11222333000181", "ACME COMERCIO LTDA"),
+                company("11444777000161", "ACME ALIMENTOS LTDA"),
+            ],
+        }
+    }
+
+    #[test]
+    fn edits_entities_by_formatted_id() {
+        let mut registry = registry();
+        let edited = registry
+            .edit(
+                "11.222.333/0001-81",
+                company("11.222.333/0001-81", "ACME LTDA"),
+            )
+            .unwrap();
+        assert_eq!(edited.id, "11222333000181");
+        assert_eq!(
+            registry.get("11222333000181").unwrap().canonical_name,
+            "ACME LTDA"
+        );
+
+        assert_eq!(
+            registry
+                .edit(
+                    "06.990.590/0001-23",
+                    company("06.990.590/0001-23", "OUTRA LTDA")
+                )
+                .unwrap_err(),
+            "Entity not found: 06990590000123"
+        );
+    }
+
+    #[test]
+    fn merges_entities_by_formatted_id() {
+        let mut registry = registry();
+        let merged = registry
+            .merge("11.222.333/0001-81", &["11.444.777/0001-61".to_string()])
+            .unwrap();
+        assert_eq!(merged.aliases, ["ACME ALIMENTOS LTDA"]);
+        assert_eq!(registry.entities.len(), 1);
+    }
+
+    #[test]
+    fn refuses_to_merge_unknown_entities() {
+        let mut registry = registry();
+        let unknown_source = registry.merge(
+            "11222333000181",
+            &["11444777000161".to_string(), "06990590000123".to_string()],
+        );
+        assert_eq!(
+            unknown_source.unwrap_err(),
+            "Entity not found: 06990590000123"
+        );
+        assert_eq!(
+            registry.merge("06990590000123", &[]).unwrap_err(),
+            "Entity not found: 06990590000123"
+        );
+        assert_eq!(registry.entities.len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::process::Command;
+use tauri::{async_runtime, Manager};
+
+use crate::llm::anthropic_api_key;
+use crate::rasterize::RENDERERS;
+use crate::settings::load_settings;
+use crate::toolchain::{refresh_toolchain, ResolvedTool, Tool, Toolchain};
+
+/// Tools without which documents cannot be finished. Rendering needs any one of `RENDERERS`.
+const REQUIRED_TOOLS: &[Tool] = &[Tool::Ocrmypdf, Tool::Tesseract];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolReport {
+    #[serde(flatten)]
+    pub resolved: ResolvedTool,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentReport {
+    pub tools: Vec<ToolReport>,
+    pub tesseract_languages: Vec<String>,
+    pub missing_languages: Vec<String>,
+    pub api_key_present: bool,
+    /// The tool page images are rendered with, when one is available.
+    pub renderer: Option<Tool>,
+    /// Whether everything required is in place, so the UI can skip the setup screen.
+    pub ready: bool,
+    /// Whether the tools are still being detected after startup, in which case nothing else is
+    /// reported yet.
+    pub detecting: bool,
+}
+
+impl EnvironmentReport {
+    fn detecting() -> Self {
+        Self {
+            tools: Vec::new(),
+            tesseract_languages: Vec::new(),
+            missing_languages: Vec::new(),
+            api_key_present: false,
+            renderer: None,
+            ready: false,
+            detecting: true,
+        }
+    }
+}
+
+/// Lists the language packs of a tesseract installation, as printed by `--list-langs`.
+fn tesseract_languages(tools: &[ResolvedTool]) -> Vec<String> {
+    let Some(path) = tools
+        .iter()
+        .find(|resolved| resolved.tool == Tool::Tesseract)
+        .and_then(|resolved| resolved.path.as_ref())
+    else {
+        return Vec::new();
+    };
+    let Ok(output) = Command::new(path).arg("--list-langs").output() else {
+        return Vec::new();
+    };
+
+    // The first line is a header such as `List of available languages in "..." (3):`.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn environment_report(handle: &tauri::AppHandle) -> EnvironmentReport {
+    refresh_toolchain(handle);
+    let tools = handle
+        .state::<Toolchain>()
+        .tools
+        .lock()
+        .expect("Toolchain lock poisoned")
+        .clone();
+
+    let tesseract_languages = tesseract_languages(&tools);
+    let mut required_languages: Vec<String> = load_settings(handle)
+        .map(|settings| settings.ocr_profiles)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|profile| profile.required_languages())
+        .collect();
+    required_languages.sort();
+    required_languages.dedup();
+    let missing_languages: Vec<String> = required_languages
+        .into_iter()
+        .filter(|language| !tesseract_languages.contains(language))
+        .collect();
+    let api_key_present = anthropic_api_key().is_ok_and(|key| !key.trim().is_empty());
+
+    let renderer = RENDERERS.iter().copied().find(|renderer| {
+        tools
+            .iter()
+            .any(|resolved| resolved.tool == *renderer && resolved.supported)
+    });
+
+    let tools: Vec<ToolReport> = tools
+        .into_iter()
+        .map(|resolved| ToolReport {
+            required: REQUIRED_TOOLS.contains(&resolved.tool),
+            resolved,
+        })
+        .collect();
+    let ready = api_key_present
+        && renderer.is_some()
+        && missing_languages.is_empty()
+        && tools
+            .iter()
+            .all(|report| !report.required || report.resolved.supported);
+
+    EnvironmentReport {
+        tools,
+        tesseract_languages,
+        missing_languages,
+        api_key_present,
+        renderer,
+        ready,
+        detecting: false,
+    }
+}
+
+/// Reports the external tools, their versions, the tesseract language packs and whether an API
+/// key is configured, so the UI can tell the user what to install on first launch. Until the
+/// detection started at launch finishes, the report only says it is detecting.
+#[tauri::command]
+pub async fn check_environment(handle: tauri::AppHandle) -> Result<EnvironmentReport, String> {
+    if !handle.state::<Toolchain>().is_detected() {
+        return Ok(EnvironmentReport::detecting());
+    }
+    async_runtime::spawn_blocking(move || environment_report(&handle))
+        .await
+        .map_err(|e| format!("Failed to check environment: {}", e))
+}
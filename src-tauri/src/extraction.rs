@@ -0,0 +1,94 @@
+use std::{fs, path::Path};
+use tauri_plugin_http::reqwest;
+
+use crate::cartao_cnpj::{self, parse_cartao_cnpj, register_company};
+use crate::entities::load_registry;
+use crate::llm::models::DocumentInfo;
+use crate::llm::prompts::STRUCTURED_DATA_PROMPT;
+use crate::llm::{anthropic_api_key, process_xml, read_json_file, save_json_file};
+use crate::transcription::xml_path_for;
+
+pub mod schemas;
+use schemas::*;
+
+/// Cuts the `<root>...</root>` element out of the model response.
+fn extract_element<'a>(response: &'a str, root: &str) -> Result<&'a str, String> {
+    let start = response
+        .find(&format!("<{}>", root))
+        .ok_or_else(|| format!("Response does not contain <{}>", root))?;
+    let closing = format!("</{}>", root);
+    let end = response
+        .rfind(&closing)
+        .ok_or_else(|| format!("Response does not contain {}", closing))?;
+    if end < start {
+        return Err(format!(
+            "Response does not contain a valid <{}> element",
+            root
+        ));
+    }
+    Ok(&response[start..end + closing.len()])
+}
+
+/// Second extraction pass: fills the schema of the document type from the cached transcription.
+/// Companies read from a cartão CNPJ are added to the entity registry.
+#[tauri::command]
+pub async fn extract_structured_data(
+    handle: tauri::AppHandle,
+    path: String,
+) -> Result<DocumentInfo, String> {
+    let json_path = Path::new(&path);
+    let mut document_info = read_json_file(json_path)?;
+
+    let canonical_type = document_info
+        .canonical_type
+        .as_ref()
+        .ok_or("Document type is not part of the taxonomy")?;
+    let schema = schema_for(&canonical_type.id).ok_or_else(|| {
+        format!(
+            "No extraction schema for document type: {}",
+            canonical_type.id
+        )
+    })?;
+
+    let xml_content = fs::read_to_string(xml_path_for(json_path))
+        .map_err(|e| format!("Failed to read transcription: {}", e))?;
+
+    // The cartão CNPJ has a fixed layout that is parsed locally, the model is only a fallback.
+    if canonical_type.id == cartao_cnpj::DOCUMENT_TYPE {
+        match parse_cartao_cnpj(&xml_content) {
+            Ok(record) => {
+                register_company(&handle, &mut document_info, record)?;
+                document_info.resolved_entities = load_registry(&handle)?.resolve(&document_info);
+                let serialized_json = serde_json::to_string(&document_info)
+                    .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+                save_json_file(&serialized_json, json_path)?;
+                return Ok(document_info);
+            }
+            Err(e) => println!("Falling back to the model for the cartão CNPJ: {}", e),
+        }
+    }
+
+    let prompt = STRUCTURED_DATA_PROMPT
+        .replace("{TYPE_NAME}", &canonical_type.type_name)
+        .replace("{SCHEMA}", &skeleton(schema))
+        .replace("{ROOT}", schema.root)
+        .replace("{XML}", &xml_content);
+
+    let client = reqwest::Client::new();
+    let api_key = anthropic_api_key()?;
+    let response = process_xml(&client, &api_key, &prompt).await?;
+
+    match schema.parse(extract_element(&response, schema.root)?)? {
+        StructuredData::CartaoCnpj(record) => {
+            register_company(&handle, &mut document_info, *record)?;
+            document_info.resolved_entities = load_registry(&handle)?.resolve(&document_info);
+        }
+        data => document_info.structured_data = Some(data),
+    }
+
+    let serialized_json = serde_json::to_string(&document_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    save_json_file(&serialized_json, json_path)?;
+
+    Ok(document_info)
+}
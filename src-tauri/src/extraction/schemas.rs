@@ -0,0 +1,468 @@
+use quick_xml::de::from_str;
+use rust_decimal::Decimal;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use crate::entities::{is_valid_cnpj, is_valid_cpf};
+
+/// Structured data extracted from a document, tagged with the canonical document type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StructuredData {
+    Nfe(NfeData),
+    ContratoSocial(ContratoSocialData),
+    ComprovanteResidencia(ComprovanteResidenciaData),
+    CartaoCnpj(Box<CartaoCnpjData>),
+    Identidade(IdentidadeData),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Party {
+    pub name: String,
+    pub document: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Address {
+    pub street: String,
+    pub number: String,
+    pub complement: String,
+    pub district: String,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NfeData {
+    pub access_key: String,
+    pub number: String,
+    pub series: String,
+    pub issue_date: String,
+    pub emitter: Party,
+    pub recipient: Party,
+    #[serde(deserialize_with = "decimal")]
+    pub total: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Partner {
+    pub name: String,
+    pub cpf: String,
+    pub role: String,
+    #[serde(deserialize_with = "decimal")]
+    pub share: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContratoSocialData {
+    pub company_name: String,
+    pub cnpj: String,
+    pub nire: String,
+    #[serde(deserialize_with = "decimal")]
+    pub capital: Option<Decimal>,
+    pub address: Address,
+    #[serde(deserialize_with = "list")]
+    pub partners: Vec<Partner>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComprovanteResidenciaData {
+    pub holder: String,
+    pub holder_document: String,
+    pub issuer: String,
+    pub address: Address,
+    pub reference_month: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Activity {
+    pub code: String,
+    pub description: String,
+}
+
+/// The company record of a comprovante de inscrição e situação cadastral (cartão CNPJ).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CartaoCnpjData {
+    pub cnpj: String,
+    pub establishment: String,
+    pub opening_date: String,
+    pub company_name: String,
+    pub trade_name: String,
+    pub size: String,
+    pub main_activity: Activity,
+    #[serde(deserialize_with = "list")]
+    pub secondary_activities: Vec<Activity>,
+    pub legal_nature: Activity,
+    pub address: Address,
+    pub email: String,
+    pub phone: String,
+    pub registration_status: String,
+    pub registration_status_date: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdentidadeData {
+    pub name: String,
+    pub cpf: String,
+    pub document_number: String,
+    pub birth_date: String,
+}
+
+/// Problems found in the extracted data of a document.
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn required(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.0.push(format!("{} is missing", field));
+        }
+    }
+
+    /// Checks the check digits of a CNPJ, a CPF, or either one, when the field is filled in.
+    fn identifier(&mut self, field: &str, value: &str, valid: &[fn(&str) -> bool]) {
+        if !value.trim().is_empty() && !valid.iter().any(|valid| valid(value)) {
+            self.0
+                .push(format!("{} is not a valid identifier: {}", field, value));
+        }
+    }
+}
+
+const CNPJ: &[fn(&str) -> bool] = &[is_valid_cnpj];
+const CPF: &[fn(&str) -> bool] = &[is_valid_cpf];
+const CNPJ_OR_CPF: &[fn(&str) -> bool] = &[is_valid_cnpj, is_valid_cpf];
+
+impl StructuredData {
+    /// Checks that the fields a document of the type always has are filled in and that its
+    /// CNPJs and CPFs have valid check digits.
+    fn validate(&self) -> Result<(), String> {
+        let mut problems = Problems::default();
+        match self {
+            StructuredData::Nfe(data) => {
+                problems.required("access_key", &data.access_key);
+                problems.required("number", &data.number);
+                problems.required("issue_date", &data.issue_date);
+                problems.required("emitter.name", &data.emitter.name);
+                problems.required("emitter.document", &data.emitter.document);
+                problems.identifier("emitter.document", &data.emitter.document, CNPJ_OR_CPF);
+                problems.identifier("recipient.document", &data.recipient.document, CNPJ_OR_CPF);
+                if data.total.is_none() {
+                    problems.0.push("total is missing".to_string());
+                }
+            }
+            StructuredData::ContratoSocial(data) => {
+                problems.required("company_name", &data.company_name);
+                problems.required("cnpj", &data.cnpj);
+                problems.identifier("cnpj", &data.cnpj, CNPJ);
+                for partner in &data.partners {
+                    problems.required("partners.name", &partner.name);
+                    problems.identifier("partners.cpf", &partner.cpf, CNPJ_OR_CPF);
+                }
+            }
+            StructuredData::ComprovanteResidencia(data) => {
+                problems.required("holder", &data.holder);
+                problems.required("address.street", &data.address.street);
+                problems.identifier("holder_document", &data.holder_document, CNPJ_OR_CPF);
+            }
+            StructuredData::CartaoCnpj(data) => {
+                problems.required("cnpj", &data.cnpj);
+                problems.required("company_name", &data.company_name);
+                problems.identifier("cnpj", &data.cnpj, CNPJ);
+            }
+            StructuredData::Identidade(data) => {
+                problems.required("name", &data.name);
+                problems.identifier("cpf", &data.cpf, CPF);
+            }
+        }
+
+        if problems.0.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.0.join("; "))
+        }
+    }
+}
+
+/// An extraction schema: the XML skeleton given to the model and how to validate its answer.
+pub struct ExtractionSchema {
+    pub document_type: &'static str,
+    pub root: &'static str,
+    pub skeleton: &'static str,
+    parse: fn(&str) -> Result<StructuredData, quick_xml::DeError>,
+}
+
+impl ExtractionSchema {
+    pub fn parse(&self, xml: &str) -> Result<StructuredData, String> {
+        let data = (self.parse)(xml)
+            .map_err(|e| format!("Extracted data does not match the schema: {}", e))?;
+        data.validate()
+            .map_err(|e| format!("Extracted data is incomplete or invalid: {}", e))?;
+        Ok(data)
+    }
+}
+
+const ADDRESS_SKELETON: &str = r#"<street>[Street name]</street>
+        <number>[Street number]</number>
+        <complement>[Complement, if any]</complement>
+        <district>[District/bairro]</district>
+        <city>[City]</city>
+        <state>[State abbreviation, e.g. SP]</state>
+        <postal_code>[CEP, digits only]</postal_code>"#;
+
+const IDENTIDADE_SKELETON: &str = r#"<identidade>
+    <name>[Full name of the holder]</name>
+    <cpf>[Holder CPF]</cpf>
+    <document_number>[Number of the document (RG number or CNH registration)]</document_number>
+    <birth_date>[Birth date as YYYY-MM-DD]</birth_date>
+</identidade>"#;
+
+pub fn schema_for(document_type: &str) -> Option<&'static ExtractionSchema> {
+    SCHEMAS
+        .iter()
+        .find(|schema| schema.document_type == document_type)
+}
+
+pub fn skeleton(schema: &ExtractionSchema) -> String {
+    schema.skeleton.replace("{ADDRESS}", ADDRESS_SKELETON)
+}
+
+/// Whether an amount is written with a decimal point, as in `1234.56`: a single dot followed by
+/// one or two digits. Any other dot is a thousands separator, as in `1.234`.
+fn has_decimal_point(value: &str) -> bool {
+    !value.contains(',')
+        && value.matches('.').count() == 1
+        && value.rsplit_once('.').is_some_and(|(_, fraction)| {
+            (1..=2).contains(&fraction.len()) && fraction.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Deserializes an optional decimal written either as `1234.56` or in the Brazilian format, read
+/// the same way as `parse_brl` reads amounts in the transcription.
+fn decimal<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) if has_decimal_point(value) => {
+            Decimal::from_str(value.trim_start_matches("R$").trim())
+                .map(Some)
+                .map_err(de::Error::custom)
+        }
+        Some(value) => crate::amounts::parse_brl(value)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("invalid amount: {}", value))),
+    }
+}
+
+/// Deserializes a list either from a JSON array or from repeated `<item>` elements.
+fn list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct ListVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> de::Visitor<'de> for ListVisitor<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of items")
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut items = Vec::new();
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+            Ok(items)
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut items = Vec::new();
+            while let Some(key) = map.next_key::<String>()? {
+                if key == "item" {
+                    items.push(map.next_value()?);
+                } else {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+            Ok(items)
+        }
+
+        fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+    }
+
+    deserializer.deserialize_any(ListVisitor(PhantomData))
+}
+
+pub const SCHEMAS: &[ExtractionSchema] = &[
+    ExtractionSchema {
+        document_type: "nfe",
+        root: "nfe",
+        skeleton: r#"<nfe>
+    <access_key>[The 44 digit access key, digits only]</access_key>
+    <number>[Invoice number]</number>
+    <series>[Invoice series]</series>
+    <issue_date>[Issue date as YYYY-MM-DD]</issue_date>
+    <emitter>
+        <name>[Emitter name]</name>
+        <document>[Emitter CNPJ or CPF]</document>
+    </emitter>
+    <recipient>
+        <name>[Recipient name]</name>
+        <document>[Recipient CNPJ or CPF]</document>
+    </recipient>
+    <total>[Total value of the invoice]</total>
+</nfe>"#,
+        parse: |xml| from_str(xml).map(StructuredData::Nfe),
+    },
+    ExtractionSchema {
+        document_type: "contrato_social",
+        root: "contrato_social",
+        skeleton: r#"<contrato_social>
+    <company_name>[Company name (razão social)]</company_name>
+    <cnpj>[Company CNPJ]</cnpj>
+    <nire>[NIRE registration number]</nire>
+    <capital>[Share capital value]</capital>
+    <address>
+        {ADDRESS}
+    </address>
+    <partners>
+        <item>
+            <name>[Partner name]</name>
+            <cpf>[Partner CPF or CNPJ]</cpf>
+            <role>[Partner role, e.g. sócio administrador]</role>
+            <share>[Value of the partner's quotas]</share>
+        </item>
+        [Repeat <item> for every partner]
+    </partners>
+</contrato_social>"#,
+        parse: |xml| from_str(xml).map(StructuredData::ContratoSocial),
+    },
+    ExtractionSchema {
+        document_type: "comprovante_residencia",
+        root: "comprovante_residencia",
+        skeleton: r#"<comprovante_residencia>
+    <holder>[Name of the account holder]</holder>
+    <holder_document>[Holder CPF or CNPJ, if present]</holder_document>
+    <issuer>[Company that issued the bill]</issuer>
+    <address>
+        {ADDRESS}
+    </address>
+    <reference_month>[Reference month as YYYY-MM]</reference_month>
+</comprovante_residencia>"#,
+        parse: |xml| from_str(xml).map(StructuredData::ComprovanteResidencia),
+    },
+    ExtractionSchema {
+        document_type: "cartao_cnpj",
+        root: "cartao_cnpj",
+        skeleton: r#"<cartao_cnpj>
+    <cnpj>[Número de inscrição (CNPJ)]</cnpj>
+    <company_name>[Nome empresarial]</company_name>
+    <trade_name>[Título do estabelecimento (nome de fantasia)]</trade_name>
+    <address>
+        {ADDRESS}
+    </address>
+    <registration_status>[Situação cadastral]</registration_status>
+</cartao_cnpj>"#,
+        parse: |xml| from_str(xml).map(|data| StructuredData::CartaoCnpj(Box::new(data))),
+    },
+    ExtractionSchema {
+        document_type: "rg",
+        root: "identidade",
+        skeleton: IDENTIDADE_SKELETON,
+        parse: |xml| from_str(xml).map(StructuredData::Identidade),
+    },
+    ExtractionSchema {
+        document_type: "cnh",
+        root: "identidade",
+        skeleton: IDENTIDADE_SKELETON,
+        parse: |xml| from_str(xml).map(StructuredData::Identidade),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(document_type: &str, xml: &str) -> Result<StructuredData, String> {
+        schema_for(document_type).unwrap().parse(xml)
+    }
+
+    fn nfe(total: &str, emitter_document: &str) -> String {
+        format!(
+            "<nfe><access_key>3524</access_key><number>1</number><issue_date>2024-03-15</issue_date>\
+             <emitter><name>Acme</name><document>{}</document></emitter>\
+             <total>{}</total></nfe>",
+            emitter_document, total
+        )
+    }
+
+    fn total(xml: &str) -> Option<Decimal> {
+        match parse("nfe", xml).unwrap() {
+            StructuredData::Nfe(data) => data.total,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn reads_amounts_like_the_transcription() {
+        let cnpj = "11.222.333/0001-81";
+        for (text, expected) in [
+            ("1.234", "1234"),
+            ("1.234,56", "1234.56"),
+            ("R$ 1.234.567,89", "1234567.89"),
+            ("1234.56", "1234.56"),
+            ("0.5", "0.5"),
+        ] {
+            assert_eq!(
+                total(&nfe(text, cnpj)),
+                Some(Decimal::from_str(expected).unwrap()),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_missing_fields_and_invalid_identifiers() {
+        assert!(parse("nfe", &nfe("10,00", "11.222.333/0001-81")).is_ok());
+        assert!(parse("nfe", &nfe("10,00", "529.982.247-25")).is_ok());
+        assert!(parse("nfe", &nfe("10,00", "11.222.333/0001-82")).is_err());
+        assert!(parse("nfe", &nfe("", "11.222.333/0001-81")).is_err());
+
+        let error =
+            parse("cartao_cnpj", "<cartao_cnpj><cnpj>123</cnpj></cartao_cnpj>").unwrap_err();
+        assert!(error.contains("company_name is missing"), "{}", error);
+        assert!(
+            error.contains("cnpj is not a valid identifier"),
+            "{}",
+            error
+        );
+
+        assert!(parse("rg", "<identidade><name>Maria</name></identidade>").is_ok());
+        assert!(parse(
+            "rg",
+            "<identidade><name>Maria</name><cpf>111.111.111-11</cpf></identidade>"
+        )
+        .is_err());
+    }
+}
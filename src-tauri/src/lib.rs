@@ -1,7 +1,55 @@
+mod amounts;
+mod cartao_cnpj;
+mod collision;
+mod dossier;
+mod duplicates;
+mod entities;
+mod environment;
+mod extraction;
 mod llm;
+mod naming;
+mod ocr;
+mod page_analysis;
+mod pdf;
 mod processor;
+mod rasterize;
+mod segmentation;
+mod settings;
+mod staging;
+mod taxonomy;
+mod text;
+mod toolchain;
+mod transcription;
+mod utility;
+mod validity;
+mod verification;
+mod workspace;
+use amounts::extract_document_amounts;
+use cartao_cnpj::import_cartao_cnpj;
+use collision::preview_finished_path;
+use dossier::{
+    add_dossier_documents, check_dossier, create_dossier, delete_dossier, dossier_checklist, get_dossier,
+    get_dossier_types, list_dossiers, remove_dossier_document,
+};
+use duplicates::{check_archive_duplicate, check_duplicate_pages};
+use entities::{
+    add_entity, edit_entity, list_entities, merge_entities, resolve_document_entities,
+};
+use environment::check_environment;
+use extraction::extract_structured_data;
 use llm::{anthropic_pipeline, update_file_name, rename_finished_document};
+use naming::{apply_naming_template, preview_naming_template};
+use page_analysis::{
+    detect_blank_pages, detect_page_orientation, set_page_rotation, set_page_status,
+};
 use processor::{final_pipeline, open_in_explorer};
+use rasterize::rasterize_pdf;
+use segmentation::propose_segmentation;
+use settings::{get_settings, update_settings};
+use taxonomy::get_document_types;
+use toolchain::{refresh_toolchain, Toolchain};
+use utility::OcrLimiter;
+use validity::{export_deadlines_ics, list_expiring_documents};
 
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -11,12 +59,54 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(Toolchain::default())
+        .manage(OcrLimiter::default())
+        .setup(|app| {
+            // Running every tool for its version takes a while, so the window opens meanwhile.
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || refresh_toolchain(&handle));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             anthropic_pipeline,
             update_file_name,
             final_pipeline,
             open_in_explorer,
-            rename_finished_document
+            rename_finished_document,
+            get_settings,
+            update_settings,
+            preview_naming_template,
+            apply_naming_template,
+            get_document_types,
+            list_entities,
+            add_entity,
+            edit_entity,
+            merge_entities,
+            resolve_document_entities,
+            extract_document_amounts,
+            extract_structured_data,
+            get_dossier_types,
+            create_dossier,
+            list_dossiers,
+            get_dossier,
+            add_dossier_documents,
+            remove_dossier_document,
+            delete_dossier,
+            dossier_checklist,
+            check_dossier,
+            list_expiring_documents,
+            export_deadlines_ics,
+            import_cartao_cnpj,
+            propose_segmentation,
+            detect_blank_pages,
+            set_page_status,
+            detect_page_orientation,
+            set_page_rotation,
+            check_duplicate_pages,
+            check_archive_duplicate,
+            rasterize_pdf,
+            check_environment,
+            preview_finished_path
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
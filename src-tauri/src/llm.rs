@@ -1,7 +1,6 @@
 use base64::prelude::*;
 use dotenv::dotenv;
 use quick_xml::{de::from_str, Reader, Writer};
-use regex::Regex;
 use serde_json::json;
 use std::fs;
 use std::io::Cursor;
@@ -13,21 +12,45 @@ use std::{
 use tauri_plugin_http::reqwest;
 use tokio::time::{sleep, Duration};
 
+use crate::amounts::extract_amounts_from_xml;
+use crate::cartao_cnpj::{self, register_cartao_cnpj};
+use crate::collision::{back_up, finished_pdf_path, resolve_destination};
+use crate::duplicates::{content_hash, update_archived_path};
+use crate::entities::load_registry;
+use crate::naming::{render_file_name, NameComponents};
+use crate::page_analysis::upright_image_path;
+use crate::settings::load_settings;
+use crate::taxonomy::load_taxonomy;
+use crate::transcription::page_xml_path;
+use crate::validity::derive_validity;
+use crate::workspace::{load_workspace, Workspace};
+
 pub mod models;
 use models::*;
 
-mod prompts;
+pub(crate) mod prompts;
 use prompts::*;
 
 #[tauri::command]
-pub async fn anthropic_pipeline(paths: Vec<String>) -> Result<DocumentInfo, String> {
-    dotenv().ok();
+pub async fn anthropic_pipeline(
+    handle: tauri::AppHandle,
+    paths: Vec<String>,
+    pages: Vec<u32>,
+) -> Result<DocumentInfo, String> {
+    let settings = load_settings(&handle)?;
+    let taxonomy = load_taxonomy(&handle)?;
     let client = reqwest::Client::new();
-    let api_key = std::env::var("ANTHROPIC_API_KEY").map_err(|e| e.to_string())?;
-    let page_numbers: Vec<String> = paths
-        .iter()
-        .map(|path| extract_page_number(path).to_string())
-        .collect();
+    let api_key = anthropic_api_key()?;
+    let workspace = match paths.first().and_then(|path| Path::new(path).parent()) {
+        Some(data_dir) => load_workspace(data_dir)?,
+        None => Workspace::default(),
+    };
+    let (paths, pages): (Vec<String>, Vec<u32>) =
+        workspace.content_pages(&paths, &pages)?.into_iter().unzip();
+    if paths.is_empty() {
+        return Err("No pages to process: the selected pages are blank or separators".to_string());
+    }
+    let page_numbers: Vec<String> = pages.iter().map(|page| page.to_string()).collect();
 
     let file_name = format!("document_page_{}", page_numbers.join("_"));
     let first_path = Path::new(&paths[0]);
@@ -44,14 +67,16 @@ pub async fn anthropic_pipeline(paths: Vec<String>) -> Result<DocumentInfo, Stri
     let xml_content = if xml_path.exists() {
         read_existing_file(&xml_path)?
     } else {
-        let vec_strings = process_images(&client, &api_key, &paths).await?;
+        let vec_strings = process_images(&client, &api_key, &paths, &pages).await?;
         let combined_xml = vec_strings.join("\n");
         let formatted_xml = format_xml(&combined_xml)?;
         save_xml_file(&formatted_xml, &xml_path)?;
         formatted_xml
     };
 
-    let prompt = FILE_NAME_GENERATION_PROMPT.replace("{XML}", &xml_content);
+    let prompt = FILE_NAME_GENERATION_PROMPT
+        .replace("{DOCUMENT_TYPES}", &taxonomy.prompt_list())
+        .replace("{XML}", &xml_content);
     let response = process_xml(&client, &api_key, &prompt).await?;
     let json_path_str = json_path.to_str().unwrap().to_string();
     let wrapped_xml = format!(
@@ -64,6 +89,28 @@ pub async fn anthropic_pipeline(paths: Vec<String>) -> Result<DocumentInfo, Stri
         serde_json::from_str(&json_content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
     document_info.json_file_path = json_path.to_str().unwrap().to_string();
+    document_info.pages = pages;
+    document_info.canonical_type = taxonomy.match_document_type(
+        &document_info.reasoning.document_type.type_name,
+        &document_info.reasoning.type_abbreviation.type_abbr,
+    );
+    let is_cartao_cnpj = document_info
+        .canonical_type
+        .as_ref()
+        .is_some_and(|canonical| canonical.id == cartao_cnpj::DOCUMENT_TYPE);
+    if is_cartao_cnpj {
+        if let Err(e) = register_cartao_cnpj(&handle, &mut document_info, &xml_content) {
+            println!("Failed to import cartão CNPJ: {}", e);
+        }
+    }
+    document_info.resolved_entities = load_registry(&handle)?.resolve(&document_info);
+    document_info.amounts = extract_amounts_from_xml(&xml_content)?;
+    document_info.content_hash = Some(content_hash(&xml_content)?);
+    document_info.validity = derive_validity(&document_info, &taxonomy)?;
+    document_info.file_name = render_file_name(
+        &settings.naming_template,
+        &NameComponents::from_document(&document_info),
+    )?;
 
     let serialized_json = serde_json::to_string(&document_info)
         .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
@@ -73,11 +120,9 @@ pub async fn anthropic_pipeline(paths: Vec<String>) -> Result<DocumentInfo, Stri
     Ok(document_info)
 }
 
-fn extract_page_number(input: &str) -> &str {
-    let re = Regex::new(r"page-(\d+)").expect("Regex should never fail");
-    re.captures(input)
-        .and_then(|caps| caps.get(1).map(|m| m.as_str()))
-        .unwrap_or("unidentified")
+pub(crate) fn anthropic_api_key() -> Result<String, String> {
+    dotenv().ok();
+    std::env::var("ANTHROPIC_API_KEY").map_err(|e| e.to_string())
 }
 
 fn read_existing_file(xml_path: &Path) -> Result<String, String> {
@@ -93,15 +138,34 @@ async fn process_images(
     client: &reqwest::Client,
     api_key: &str,
     paths: &[String],
+    pages: &[u32],
 ) -> Result<Vec<String>, String> {
     let mut vec_strings = Vec::new();
-    for path in paths {
-        let result = process_image(client, api_key, path).await?;
+    for (path, page) in paths.iter().zip(pages) {
+        let result = transcribe_page(client, api_key, path, *page).await?;
         vec_strings.push(result);
     }
     Ok(vec_strings)
 }
 
+/// Transcribes a single page image, reusing the `page-N.xml` cached next to it when present.
+pub(crate) async fn transcribe_page(
+    client: &reqwest::Client,
+    api_key: &str,
+    path: &str,
+    page: u32,
+) -> Result<String, String> {
+    let xml_path = page_xml_path(Path::new(path));
+    if xml_path.exists() {
+        return read_existing_file(&xml_path);
+    }
+
+    let image_path = upright_image_path(path)?;
+    let formatted_xml = format_xml(&process_image(client, api_key, &image_path, page).await?)?;
+    save_xml_file(&formatted_xml, &xml_path)?;
+    Ok(formatted_xml)
+}
+
 fn xml_to_json(xml: &str) -> Result<String, String> {
     let document: DocumentInfo =
         from_str(xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
@@ -150,16 +214,19 @@ async fn process_image(
     client: &reqwest::Client,
     api_key: &str,
     path: &str,
+    page: u32,
 ) -> Result<String, String> {
-    let page_number = extract_page_number(path);
+    let page_number = page.to_string();
     let base64_image = encode_image_to_base64(path)?;
+    let media_type = image_media_type(path);
     let prefilled_message = format!("<page number=\"{page_number}\">");
 
     let max_retries = 5;
     let mut retry_count = 0;
 
     loop {
-        let response = send_anthropic_request(client, api_key, &base64_image, page_number).await?;
+        let response =
+            send_anthropic_request(client, api_key, &base64_image, media_type, &page_number).await?;
 
         println!("Response: {:?}", response);
         print_rate_limit_headers(&response);
@@ -175,6 +242,14 @@ async fn process_image(
     }
 }
 
+fn image_media_type(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        _ => "image/webp",
+    }
+}
+
 fn encode_image_to_base64(path: &str) -> Result<String, String> {
     let mut file = File::open(path).map_err(|e| e.to_string())?;
     let mut buffer = Vec::new();
@@ -186,6 +261,7 @@ async fn send_anthropic_request(
     client: &reqwest::Client,
     api_key: &str,
     base64_image: &str,
+    media_type: &str,
     page_number: &str,
 ) -> Result<reqwest::Response, String> {
     client
@@ -209,7 +285,7 @@ async fn send_anthropic_request(
                             "type": "image",
                             "source": {
                                 "type": "base64",
-                                "media_type": "image/webp",
+                                "media_type": media_type,
                                 "data": base64_image,
                             },
                         },
@@ -280,7 +356,7 @@ fn print_rate_limit_headers(response: &reqwest::Response) {
     }
 }
 
-async fn process_xml(
+pub(crate) async fn process_xml(
     client: &reqwest::Client,
     api_key: &str,
     prompt: &str,
@@ -323,14 +399,14 @@ async fn process_xml(
     }
 }
 
-fn read_json_file(json_path: &Path) -> Result<DocumentInfo, String> {
+pub(crate) fn read_json_file(json_path: &Path) -> Result<DocumentInfo, String> {
     let json_content =
         fs::read_to_string(json_path).map_err(|e| format!("Failed to read JSON file: {}", e))?;
 
     serde_json::from_str(&json_content).map_err(|e| format!("Failed to parse JSON: {}", e))
 }
 
-fn save_json_file(json_content: &str, json_path: &Path) -> Result<(), String> {
+pub(crate) fn save_json_file(json_content: &str, json_path: &Path) -> Result<(), String> {
     if let Some(parent) = json_path.parent() {
         create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
@@ -341,27 +417,38 @@ fn save_json_file(json_content: &str, json_path: &Path) -> Result<(), String> {
     Ok(())
 }
 
+/// Sets the file name of a document, keeping track of previous names. Returns whether it changed.
+pub(crate) fn set_file_name(document_info: &mut DocumentInfo, name: String) -> bool {
+    if document_info.file_name == name {
+        return false;
+    }
+    if document_info.file_name_history.is_empty() {
+        document_info.file_name_history.push(document_info.file_name.clone());
+    }
+    if !document_info.file_name_history.contains(&name) {
+        document_info.file_name_history.push(name.clone());
+    }
+    document_info.file_name = name;
+    true
+}
+
 #[tauri::command]
 pub fn update_file_name(path: String, name: String) -> Result<DocumentInfo, String> {
-    let mut document_info: DocumentInfo = read_json_file(&Path::new(&path))?;
-    if document_info.file_name != name {
-        if document_info.file_name_history.is_empty() {
-            document_info.file_name_history.push(document_info.file_name.clone());
-        }
-        if !document_info.file_name_history.contains(&name) {
-            document_info.file_name_history.push(name.clone());
-        }
-        document_info.file_name = name;
-
+    let mut document_info: DocumentInfo = read_json_file(Path::new(&path))?;
+    if set_file_name(&mut document_info, name) {
         let serialized_json = serde_json::to_string(&document_info)
             .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
-        save_json_file(&serialized_json, &Path::new(&path))?;
+        save_json_file(&serialized_json, Path::new(&path))?;
     }
     Ok(document_info)
 }
 
 #[tauri::command]
-pub fn rename_finished_document(old_path: String, new_name: String) -> Result<DocumentInfo, String> {
+pub fn rename_finished_document(
+    handle: tauri::AppHandle,
+    old_path: String,
+    new_name: String,
+) -> Result<DocumentInfo, String> {
     println!("Renaming document. Old path: {}, New name: {}", old_path, new_name);
 
     let json_path = Path::new(&old_path);
@@ -375,23 +462,40 @@ pub fn rename_finished_document(old_path: String, new_name: String) -> Result<Do
     let json_content = fs::read_to_string(json_path).map_err(|e| format!("Failed to read JSON: {}", e))?;
     let mut doc_info: DocumentInfo = serde_json::from_str(&json_content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
-    let current_pdf_path = done_dir.join(&doc_info.file_name).with_extension("pdf");
+    let current_pdf_path = finished_pdf_path(&done_dir, &doc_info.file_name);
     println!("Current PDF path: {:?}", current_pdf_path);
 
-    let new_pdf_name = format!("{}.pdf", new_name);
-    let new_pdf_path = done_dir.join(&new_pdf_name);
-    println!("New PDF path: {:?}", new_pdf_path);
-
     if !current_pdf_path.exists() {
         return Err(format!("Current PDF file does not exist: {:?}", current_pdf_path));
     }
 
-    fs::rename(&current_pdf_path, &new_pdf_path).map_err(|e| format!("Failed to rename PDF: {}", e))?;
+    let policy = load_settings(&handle)?.collision_policy;
+    let destination = resolve_destination(&handle, &done_dir, &new_name, &old_path, policy)?;
+    println!("New PDF path: {:?}", destination.path);
+    if destination.path == current_pdf_path {
+        return Ok(doc_info);
+    }
+    if let Some(replaced) = &destination.replaces {
+        back_up(replaced)?;
+    }
+
+    fs::rename(&current_pdf_path, &destination.path).map_err(|e| format!("Failed to rename PDF: {}", e))?;
 
-    doc_info.file_name = new_name;
+    set_file_name(&mut doc_info, destination.file_name);
 
-    let updated_json = serde_json::to_string_pretty(&doc_info).map_err(|e| format!("Failed to serialize JSON: {}", e))?;
-    fs::write(json_path, updated_json).map_err(|e| format!("Failed to write updated JSON: {}", e))?;
+    // The PDF goes back to its name if the JSON cannot follow it.
+    let written = serde_json::to_string_pretty(&doc_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))
+        .and_then(|updated_json| fs::write(json_path, updated_json).map_err(|e| format!("Failed to write updated JSON: {}", e)));
+    if let Err(e) = written {
+        if let Err(rollback) = fs::rename(&destination.path, &current_pdf_path) {
+            println!("Failed to restore PDF name: {}", rollback);
+        }
+        return Err(e);
+    }
+    if let Err(e) = update_archived_path(&handle, &doc_info, &destination.path) {
+        println!("Failed to update archived document: {}", e);
+    }
 
     Ok(doc_info)
 }
\ No newline at end of file
@@ -1,5 +1,11 @@
+use chrono::{Months, NaiveDate};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::extraction::schemas::StructuredData;
+use crate::verification::VerificationReport;
+use crate::workspace::page_number;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnthropicResponse {
     pub content: Vec<Content>,
@@ -42,12 +48,76 @@ pub struct OutputError {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DocumentInfo {
+    #[serde(default)]
     pub file_name: String,
     #[serde(default)]
     pub file_name_history: Vec<String>,
     pub pages_paths: Vec<String>,
+    /// Pages of the original scan, 1-based, in the order of the document.
+    #[serde(default)]
+    pub pages: Vec<u32>,
     pub reasoning: Reasoning,
     pub json_file_path: String,
+    #[serde(default)]
+    pub canonical_type: Option<CanonicalType>,
+    #[serde(default)]
+    pub resolved_entities: Vec<ResolvedEntity>,
+    #[serde(default)]
+    pub amounts: Vec<MonetaryAmount>,
+    #[serde(default)]
+    pub structured_data: Option<StructuredData>,
+    #[serde(default)]
+    pub validity: Option<Validity>,
+    /// Hash of the normalized text of the transcription, to recognize rescanned documents.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Pages of the document left out of the finished PDF, confirmed as blank or separators.
+    #[serde(default)]
+    pub excluded_pages: Vec<u32>,
+    /// Checks of the finished document, from the last time it was finished.
+    #[serde(default)]
+    pub verification: Option<VerificationReport>,
+}
+
+impl DocumentInfo {
+    /// Pages of the original scan, taken from the page image paths for documents processed
+    /// before the pages were recorded.
+    pub fn source_pages(&self) -> Result<Vec<u32>, String> {
+        if !self.pages.is_empty() {
+            return Ok(self.pages.clone());
+        }
+        self.pages_paths
+            .iter()
+            .map(|path| {
+                page_number(path).ok_or_else(|| format!("Unable to identify the page of {}", path))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanonicalType {
+    pub id: String,
+    pub type_name: String,
+    pub type_abbr: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedEntity {
+    pub id: String,
+    pub canonical_name: String,
+    pub short_name: String,
+    pub matched: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonetaryAmount {
+    pub label: String,
+    pub key: Option<String>,
+    pub value: Decimal,
+    pub page: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,6 +128,10 @@ pub struct Reasoning {
     pub language: String,
     pub main_entities: MainEntities,
     pub type_abbreviation: TypeAbbreviation,
+    #[serde(default)]
+    pub identifiers: Identifiers,
+    #[serde(default)]
+    pub validity: ValidityReasoning,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -79,6 +153,34 @@ pub struct ImportantDate {
     pub date: String,
 }
 
+impl ImportantDate {
+    pub fn parse(&self) -> Option<NaiveDate> {
+        parse_partial_date(&self.date)
+    }
+}
+
+/// Parses a date, taking the first day of the period for partial dates (YYYY-MM or YYYY).
+pub fn parse_partial_date(date: &str) -> Option<NaiveDate> {
+    let date = date.trim();
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d"))
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01-01", date), "%Y-%m-%d"))
+        .ok()
+}
+
+/// Parses an expiry date, taking the last day of the period for partial dates (YYYY-MM or YYYY),
+/// so that a document valid "until 2025-03" does not expire on the first of March.
+pub fn parse_partial_end_date(date: &str) -> Option<NaiveDate> {
+    let date = date.trim();
+    if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        return Some(day);
+    }
+    let period = if date.contains('-') { 1 } else { 12 };
+    parse_partial_date(date)?
+        .checked_add_months(Months::new(period))?
+        .pred_opt()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MainEntities {
     pub analysis: String,
@@ -89,4 +191,42 @@ pub struct MainEntities {
 pub struct TypeAbbreviation {
     pub analysis: String,
     pub type_abbr: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Identifiers {
+    #[serde(default)]
+    pub analysis: String,
+    #[serde(default)]
+    pub cnpj: String,
+    #[serde(default)]
+    pub cpf: String,
+    #[serde(default)]
+    pub document_number: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ValidityReasoning {
+    #[serde(default)]
+    pub analysis: String,
+    #[serde(default)]
+    pub issue_date: String,
+    #[serde(default)]
+    pub expiry_date: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValiditySource {
+    /// The expiry date is stated in the document.
+    Extracted,
+    /// The expiry date was derived from the validity rule of the document type.
+    Rule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Validity {
+    pub issue_date: Option<NaiveDate>,
+    pub expiry_date: NaiveDate,
+    pub source: ValiditySource,
 }
\ No newline at end of file
@@ -42,11 +42,11 @@ pub const DOCUMENT_PARSE_FINAL_MESSAGE: &str = r#"</page_image>
 
 Remember to be as accurate and detailed as possible in your extraction."#;
 
-pub const FILE_NAME_GENERATION_PROMPT: &str = r#"Analyze the following XML representation of a bussiness document to generate an optimal file name for it:
+pub const FILE_NAME_GENERATION_PROMPT: &str = r#"Analyze the following XML representation of a bussiness document to extract the components of an optimal file name for it:
 
 <document>{XML}</document>
 
-In order to create the file name, follow these steps below:
+In order to extract the file name components, follow these steps below:
 
 <step number="1">
     Determine the document language. Output your finding within <language> tags as follows:
@@ -70,7 +70,8 @@ In order to create the file name, follow these steps below:
 
 <step number="3">
     Extract or infer a document type name:
-    - Use existing document type names if present
+    - Prefer one of the allowed document types below whenever it fits the document
+    - Otherwise use existing document type names if present
     - If not, create one based on the document content
     - Exclude prepositions and articles
 
@@ -80,11 +81,16 @@ In order to create the file name, follow these steps below:
             <analysis>[Detailed explanation of your research process]</analysis>
             <type_name>[The derived document type name]</type_name>
         </document_type>
+
+    Allowed document types (name and abbreviation):
+
+{DOCUMENT_TYPES}
 </step>
 
 <step number="4">
     Extract or derive an abbreviation/initialism for the document type:
-    - Use existing abbreviations if present
+    - Use the abbreviation of the allowed document type chosen in step 3, if any
+    - Otherwise use existing abbreviations if present
     - If not, create one based on the document type name
     - Use UPPERCASE letters only (e.g., "Nota Fiscal Eletrônica" -> "NF-E")
     - Exclude prepositions and articles
@@ -106,7 +112,7 @@ In order to create the file name, follow these steps below:
 
         <main_entities>
             <analysis>[Detailed explanation of your research process]</analysis>
-            <entities>[The identified entities, otherwise leave this tag empty]</entities>
+            <entities>[The identified entities separated by semicolons, otherwise leave this tag empty]</entities>
         </main_entities>
 </step>
 
@@ -136,22 +142,35 @@ In order to create the file name, follow these steps below:
 </step>
 
 <step number="7">
-    Build the file name in the following format:
+    Extract the identifiers of the document (if any):
+    - The CNPJ of the entity that likely issued the document
+    - The CPF of the person the document refers to
+    - The number of the document itself (e.g. invoice number, contract number)
 
-    [YYYY-MM-DD]-[ABBR]-[doc_purp]
+    Output your analysis within <identifiers> tags as follows:
 
-    otherwise, if no date present:
-
-    [ABBR]-[doc_purp]
+        <identifiers>
+            <analysis>[Detailed explanation of your research process]</analysis>
+            <cnpj>[The identified CNPJ, otherwise leave this tag empty]</cnpj>
+            <cpf>[The identified CPF, otherwise leave this tag empty]</cpf>
+            <document_number>[The identified document number, otherwise leave this tag empty]</document_number>
+        </identifiers>
+</step>
 
-    Where:
-    - [YYYY-MM-DD] is the date from step 2
-    - [ABBR] is the abbreviation from step 4
-    - [doc_purp] is the purpose from step 6
+<step number="8">
+    Identify the validity window of the document (if any):
+    - The issue date of the document
+    - The date until which the document is valid, as stated in the document (e.g. certidões, alvarás, certificates)
+    - Format both as YYYY-MM-DD
+    - Do not infer the expiry date if the document does not state it
 
-    Finally, provide the file name (without file extension) within <file_name> tags:
+    Output your analysis within <validity> tags as follows:
 
-        <file_name>[The generated file name]</file_name>
+        <validity>
+            <analysis>[Detailed explanation of your research process]</analysis>
+            <issue_date>[The issue date, otherwise leave this tag empty]</issue_date>
+            <expiry_date>[The expiry date, otherwise leave this tag empty]</expiry_date>
+        </validity>
 </step>
 
     Ensure strict adherence to all steps above (particularly the step 6).
@@ -181,5 +200,31 @@ Remember to provide your outputs in the same language as the document after dete
         <formatting_process></formatting_process>
         <summary></summary>
     </document_summary>
-</reasoning>
-<file_name></file_name>"#;
\ No newline at end of file
+    <identifiers>
+        <analysis></analysis>
+        <cnpj></cnpj>
+        <cpf></cpf>
+        <document_number></document_number>
+    </identifiers>
+    <validity>
+        <analysis></analysis>
+        <issue_date></issue_date>
+        <expiry_date></expiry_date>
+    </validity>
+</reasoning>"#;
+
+pub const STRUCTURED_DATA_PROMPT: &str = r#"Extract the data of the following XML representation of a business document ({TYPE_NAME}) into the XML structure given below:
+
+<document>{XML}</document>
+
+Follow these rules:
+- Fill every tag with the value found in the document, otherwise leave the tag empty
+- Write dates as YYYY-MM-DD and monetary values as plain decimal numbers (e.g. 1234.56)
+- Copy names, identifiers and addresses exactly as they appear in the document
+- Do not add tags that are not part of the structure
+
+<structure>
+{SCHEMA}
+</structure>
+
+Output only the filled structure, starting with <{ROOT}> and ending with </{ROOT}>."#;
@@ -0,0 +1,385 @@
+use std::path::Path;
+
+use crate::amounts::{format_brl, main_amount};
+use crate::collision::finished_pdf_path;
+use crate::entities::split_entity_names;
+use crate::llm::models::DocumentInfo;
+use crate::llm::{read_json_file, rename_finished_document, save_json_file, set_file_name};
+use crate::text::digits_only;
+
+pub const DEFAULT_TEMPLATE: &str = "[{date}-]{abbr}-{summary}";
+
+const PLACEHOLDERS: &[&str] = &[
+    "date", "year", "month", "abbr", "type", "summary", "entity", "entities", "cnpj", "cpf",
+    "number", "language", "total",
+];
+
+const FILTERS: &[&str] = &["upper", "lower", "slug"];
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Placeholder {
+        name: String,
+        filter: Option<String>,
+    },
+    Optional(Vec<Segment>),
+}
+
+/// Values available to a naming template, taken from the structured reasoning of a document.
+#[derive(Debug, Default)]
+pub struct NameComponents {
+    pub date: String,
+    pub abbr: String,
+    pub type_name: String,
+    pub summary: String,
+    pub entities: Vec<String>,
+    pub cnpj: String,
+    pub cpf: String,
+    pub number: String,
+    pub language: String,
+    pub total: String,
+}
+
+impl NameComponents {
+    pub fn from_document(document_info: &DocumentInfo) -> Self {
+        let reasoning = &document_info.reasoning;
+        let (type_name, abbr) = match &document_info.canonical_type {
+            Some(canonical) => (canonical.type_name.clone(), canonical.type_abbr.clone()),
+            None => (
+                reasoning.document_type.type_name.trim().to_string(),
+                reasoning.type_abbreviation.type_abbr.trim().to_string(),
+            ),
+        };
+        let entities = if document_info.resolved_entities.is_empty() {
+            split_entity_names(&reasoning.main_entities.entities)
+        } else {
+            document_info
+                .resolved_entities
+                .iter()
+                .map(|entity| entity.short_name.clone())
+                .collect()
+        };
+        Self {
+            date: reasoning.important_date.date.trim().to_string(),
+            abbr,
+            type_name,
+            summary: reasoning.document_summary.summary.trim().to_string(),
+            entities,
+            cnpj: reasoning.identifiers.cnpj.trim().to_string(),
+            cpf: reasoning.identifiers.cpf.trim().to_string(),
+            number: reasoning.identifiers.document_number.trim().to_string(),
+            language: reasoning.language.trim().to_string(),
+            // Thousands separators are dropped so the dots are not taken for an extension.
+            total: main_amount(document_info)
+                .map(|amount| format_brl(&amount.value).replace('.', ""))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn value(&self, name: &str) -> String {
+        match name {
+            "date" => self.date.clone(),
+            "year" => self.date.get(0..4).unwrap_or_default().to_string(),
+            "month" => self.date.get(5..7).unwrap_or_default().to_string(),
+            "abbr" => self.abbr.clone(),
+            "type" => self.type_name.clone(),
+            "summary" => self.summary.clone(),
+            "entity" => self.entities.first().cloned().unwrap_or_default(),
+            "entities" => self.entities.join("_"),
+            "cnpj" => digits_only(&self.cnpj),
+            "cpf" => digits_only(&self.cpf),
+            "number" => self.number.clone(),
+            "language" => self.language.clone(),
+            "total" => self.total.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Parses a naming template such as `[{date}-]{abbr}-{summary}`.
+///
+/// `{name}` inserts a component, `{name|filter}` transforms it and `[...]` marks a group that is
+/// dropped entirely when any component inside it is empty.
+fn parse_segments(
+    template: &str,
+    start: usize,
+    nested: bool,
+) -> Result<(Vec<Segment>, usize), String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut position = start;
+
+    while let Some(c) = template[position..].chars().next() {
+        match c {
+            '{' => {
+                let rest = &template[position + 1..];
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed placeholder at position {}", position))?;
+                let (name, filter) = match rest[..end].split_once('|') {
+                    Some((name, filter)) => (name.trim(), Some(filter.trim().to_string())),
+                    None => (rest[..end].trim(), None),
+                };
+                if !PLACEHOLDERS.contains(&name) {
+                    return Err(format!("Unknown placeholder: {{{}}}", name));
+                }
+                if let Some(filter) = &filter {
+                    if !FILTERS.contains(&filter.as_str()) {
+                        return Err(format!("Unknown filter: {}", filter));
+                    }
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Placeholder {
+                    name: name.to_string(),
+                    filter,
+                });
+                position += end + 2;
+            }
+            '[' => {
+                if nested {
+                    return Err(format!("Nested optional group at position {}", position));
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                let (group, end) = parse_segments(template, position + 1, true)?;
+                segments.push(Segment::Optional(group));
+                position = end;
+            }
+            ']' if nested => {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(literal));
+                }
+                return Ok((segments, position + 1));
+            }
+            ']' | '}' => return Err(format!("Unexpected '{}' at position {}", c, position)),
+            _ => {
+                literal.push(c);
+                position += c.len_utf8();
+            }
+        }
+    }
+
+    if nested {
+        return Err("Unclosed optional group".to_string());
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok((segments, position))
+}
+
+fn parse_template_segments(template: &str) -> Result<Vec<Segment>, String> {
+    if template.trim().is_empty() {
+        return Err("Naming template is empty".to_string());
+    }
+    parse_segments(template, 0, false).map(|(segments, _)| segments)
+}
+
+pub fn parse_template(template: &str) -> Result<(), String> {
+    parse_template_segments(template).map(|_| ())
+}
+
+fn apply_filter(value: String, filter: Option<&str>) -> String {
+    match filter {
+        Some("upper") => value.to_uppercase(),
+        Some("lower") => value.to_lowercase(),
+        Some("slug") => {
+            let slug: String = value
+                .to_lowercase()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            slug.split('_')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("_")
+        }
+        _ => value,
+    }
+}
+
+/// Renders the segments, returning `None` when an optional group hits an empty component.
+fn render_segments(
+    segments: &[Segment],
+    components: &NameComponents,
+    optional: bool,
+) -> Option<String> {
+    let mut output = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => output.push_str(text),
+            Segment::Placeholder { name, filter } => {
+                let value = apply_filter(components.value(name), filter.as_deref());
+                if value.is_empty() && optional {
+                    return None;
+                }
+                output.push_str(&value);
+            }
+            Segment::Optional(group) => {
+                if let Some(text) = render_segments(group, components, true) {
+                    output.push_str(&text);
+                }
+            }
+        }
+    }
+    Some(output)
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !r#"<>:"/\|?*"#.contains(*c))
+        .collect();
+    sanitized
+        .trim_matches(|c: char| c == '-' || c == '_' || c == '.' || c.is_whitespace())
+        .to_string()
+}
+
+pub fn render_file_name(template: &str, components: &NameComponents) -> Result<String, String> {
+    let segments = parse_template_segments(template)?;
+    let rendered = render_segments(&segments, components, false).unwrap_or_default();
+
+    let file_name = sanitize_file_name(&rendered);
+    if file_name.is_empty() {
+        return Err("Naming template rendered an empty file name".to_string());
+    }
+    Ok(file_name)
+}
+
+#[tauri::command]
+pub fn preview_naming_template(path: String, template: String) -> Result<String, String> {
+    let document_info = read_json_file(Path::new(&path))?;
+    render_file_name(&template, &NameComponents::from_document(&document_info))
+}
+
+#[tauri::command]
+pub fn apply_naming_template(
+    handle: tauri::AppHandle,
+    paths: Vec<String>,
+) -> Result<Vec<DocumentInfo>, String> {
+    let settings = crate::settings::load_settings(&handle)?;
+    let mut documents = Vec::new();
+
+    for path in paths {
+        let json_path = Path::new(&path);
+        let mut document_info = read_json_file(json_path)?;
+        let name = render_file_name(
+            &settings.naming_template,
+            &NameComponents::from_document(&document_info),
+        )?;
+        // Finished documents are renamed together with their PDF in `done/`.
+        let done_dir = json_path
+            .parent()
+            .ok_or("Unable to get parent directory")?
+            .join("done");
+        if finished_pdf_path(&done_dir, &document_info.file_name).exists() {
+            documents.push(rename_finished_document(handle.clone(), path, name)?);
+            continue;
+        }
+        if set_file_name(&mut document_info, name) {
+            let serialized_json = serde_json::to_string(&document_info)
+                .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+            save_json_file(&serialized_json, json_path)?;
+        }
+        documents.push(document_info);
+    }
+
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components() -> NameComponents {
+        NameComponents {
+            date: "2024-03-15".to_string(),
+            abbr: "NF".to_string(),
+            type_name: "Nota Fiscal".to_string(),
+            summary: "Compra de Material".to_string(),
+            entities: vec!["Acme".to_string(), "Beta".to_string()],
+            cnpj: "11.222.333/0001-81".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_the_default_template() {
+        assert_eq!(
+            render_file_name(DEFAULT_TEMPLATE, &components()).unwrap(),
+            "2024-03-15-NF-Compra de Material"
+        );
+    }
+
+    #[test]
+    fn drops_optional_groups_with_an_empty_component() {
+        let components = NameComponents {
+            date: String::new(),
+            ..components()
+        };
+        assert_eq!(
+            render_file_name(DEFAULT_TEMPLATE, &components).unwrap(),
+            "NF-Compra de Material"
+        );
+        assert_eq!(
+            render_file_name("{abbr}[-{number}-{cnpj}]", &components).unwrap(),
+            "NF"
+        );
+    }
+
+    #[test]
+    fn renders_derived_components_and_filters() {
+        assert_eq!(
+            render_file_name("{year}-{month} {entities} {cnpj}", &components()).unwrap(),
+            "2024-03 Acme_Beta 11222333000181"
+        );
+        assert_eq!(
+            render_file_name("{abbr|lower}_{summary|slug}_{entity|upper}", &components()).unwrap(),
+            "nf_compra_de_material_ACME"
+        );
+    }
+
+    #[test]
+    fn removes_characters_not_allowed_in_file_names() {
+        let components = NameComponents {
+            summary: "Compra/venda: lote?".to_string(),
+            ..components()
+        };
+        assert_eq!(
+            render_file_name("-{summary}.", &components).unwrap(),
+            "Compravenda lote"
+        );
+    }
+
+    #[test]
+    fn rejects_an_empty_file_name() {
+        let components = NameComponents::default();
+        assert!(render_file_name("[{date}]-", &components).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in [
+            "",
+            "  ",
+            "{unknown}",
+            "{abbr|camel}",
+            "{abbr",
+            "[{date}-",
+            "[[{date}]]",
+            "{abbr}]",
+            "{abbr}}",
+        ] {
+            assert!(
+                parse_template(template).is_err(),
+                "{:?} was accepted",
+                template
+            );
+        }
+        assert!(parse_template("[{date}-]{abbr|upper} {summary}").is_ok());
+    }
+}
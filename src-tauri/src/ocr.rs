@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::llm::models::DocumentInfo;
+use crate::settings::{OutputProfile, Settings};
+use crate::text::normalize;
+
+pub const DEFAULT_PROFILE: &str = "padrao";
+/// Tesseract language used when a profile derives the language and the document's is unknown.
+const FALLBACK_LANGUAGE: &str = "por";
+/// Normalized language names and codes, as `Reasoning.language` may hold them, and their
+/// tesseract language packs.
+const LANGUAGES: &[(&[&str], &str)] = &[
+    (&["PORTUGUES", "PORTUGUESE", "PT", "PT BR", "POR"], "por"),
+    (&["INGLES", "ENGLISH", "EN", "EN US", "ENG"], "eng"),
+    (&["ESPANHOL", "SPANISH", "ESPANOL", "ES", "SPA"], "spa"),
+    (&["FRANCES", "FRENCH", "FRANCAIS", "FR", "FRA"], "fra"),
+    (&["ALEMAO", "GERMAN", "DEUTSCH", "DE", "DEU"], "deu"),
+    (&["ITALIANO", "ITALIAN", "IT", "ITA"], "ita"),
+];
+
+/// How ocrmypdf treats pages that already have text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrMode {
+    /// Rasterizes every page and runs OCR on it, discarding existing text.
+    #[default]
+    Force,
+    /// Leaves pages that already have text untouched.
+    Skip,
+    /// Replaces an existing OCR layer, keeping the rest of the page as is.
+    Redo,
+}
+
+/// Named set of options of the ocrmypdf stage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrProfile {
+    pub name: String,
+    /// Tesseract language packs. When empty, the language is derived from the document.
+    pub languages: Vec<String>,
+    pub mode: OcrMode,
+    pub deskew: bool,
+    pub rotate_pages: bool,
+    pub clean: bool,
+    /// ocrmypdf optimization level, from 0 (none) to 3 (aggressive).
+    pub optimize: u8,
+    /// Lets optimization use lossy JBIG2 for monochrome images.
+    pub jbig2_lossy: bool,
+    /// Overrides the output profile of the settings.
+    pub output_profile: Option<OutputProfile>,
+    /// Canonical document type ids this profile is used for.
+    pub document_types: Vec<String>,
+}
+
+impl Default for OcrProfile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            languages: vec![FALLBACK_LANGUAGE.to_string()],
+            mode: OcrMode::Force,
+            deskew: false,
+            rotate_pages: false,
+            clean: true,
+            optimize: 1,
+            jbig2_lossy: false,
+            output_profile: None,
+            document_types: Vec::new(),
+        }
+    }
+}
+
+impl OcrProfile {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("OCR profiles must have a name".to_string());
+        }
+        if self.optimize > 3 {
+            return Err(format!(
+                "Invalid optimization level in OCR profile {}: {}",
+                self.name, self.optimize
+            ));
+        }
+        if let Some(language) = self.languages.iter().find(|language| {
+            language.is_empty()
+                || !language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }) {
+            return Err(format!(
+                "Invalid language in OCR profile {}: {}",
+                self.name, language
+            ));
+        }
+        if self.mode == OcrMode::Redo && self.deskew {
+            return Err(format!(
+                "OCR profile {} cannot deskew pages while redoing OCR",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Languages to run tesseract with, derived from the document when none are configured.
+    pub fn languages_for(&self, document_info: &DocumentInfo) -> Vec<String> {
+        if !self.languages.is_empty() {
+            return self.languages.clone();
+        }
+        vec![document_language(&document_info.reasoning.language)
+            .unwrap_or(FALLBACK_LANGUAGE)
+            .to_string()]
+    }
+
+    /// Language packs the profile needs installed. Profiles that derive the language from the
+    /// document need at least the fallback one.
+    pub fn required_languages(&self) -> Vec<String> {
+        if self.languages.is_empty() {
+            return vec![FALLBACK_LANGUAGE.to_string()];
+        }
+        self.languages.clone()
+    }
+
+    pub fn output_profile(&self, settings: &Settings) -> OutputProfile {
+        self.output_profile.unwrap_or(settings.output_profile)
+    }
+
+    /// ocrmypdf options for a document, without the input and output files.
+    pub fn ocrmypdf_args(
+        &self,
+        document_info: &DocumentInfo,
+        output_profile: OutputProfile,
+    ) -> Vec<String> {
+        let mut args = vec![match self.mode {
+            OcrMode::Force => "--force-ocr",
+            OcrMode::Skip => "--skip-text",
+            OcrMode::Redo => "--redo-ocr",
+        }
+        .to_string()];
+        args.extend([
+            "--pdf-renderer".to_string(),
+            "hocr".to_string(),
+            "--color-conversion-strategy".to_string(),
+            "UseDeviceIndependentColor".to_string(),
+            "-l".to_string(),
+            self.languages_for(document_info).join("+"),
+        ]);
+        if self.deskew {
+            args.push("--deskew".to_string());
+        }
+        if self.rotate_pages {
+            args.push("--rotate-pages".to_string());
+        }
+        if self.clean {
+            args.push("--clean".to_string());
+        }
+        args.extend(["--optimize".to_string(), self.optimize.to_string()]);
+        if self.jbig2_lossy {
+            args.push("--jbig2-lossy".to_string());
+        }
+        args.extend([
+            "--output-type".to_string(),
+            output_profile.output_type().to_string(),
+        ]);
+        args
+    }
+}
+
+/// Tesseract language pack of a language as the transcription model names it.
+pub fn document_language(language: &str) -> Option<&'static str> {
+    let language = normalize(language);
+    LANGUAGES
+        .iter()
+        .find(|(names, _)| names.contains(&language.as_str()))
+        .map(|(_, code)| *code)
+}
+
+pub fn validate_profiles(settings: &Settings) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for profile in &settings.ocr_profiles {
+        profile.validate()?;
+        if !names.insert(profile.name.as_str()) {
+            return Err(format!("Duplicate OCR profile: {}", profile.name));
+        }
+    }
+    if !names.contains(settings.default_ocr_profile.as_str()) {
+        return Err(format!(
+            "Default OCR profile not found: {}",
+            settings.default_ocr_profile
+        ));
+    }
+    Ok(())
+}
+
+/// Profile for a document: the one asked for, else the first one configured for its document
+/// type, else the default one.
+pub fn select_profile(
+    settings: &Settings,
+    document_info: &DocumentInfo,
+    requested: Option<&str>,
+) -> Result<OcrProfile, String> {
+    if let Some(name) = requested {
+        return settings
+            .ocr_profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+            .ok_or_else(|| format!("OCR profile not found: {}", name));
+    }
+
+    let by_type = document_info.canonical_type.as_ref().and_then(|canonical| {
+        settings
+            .ocr_profiles
+            .iter()
+            .find(|profile| profile.document_types.contains(&canonical.id))
+    });
+    Ok(by_type
+        .or_else(|| {
+            settings
+                .ocr_profiles
+                .iter()
+                .find(|profile| profile.name == settings.default_ocr_profile)
+        })
+        .cloned()
+        .unwrap_or_default())
+}
@@ -0,0 +1,330 @@
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, GrayImage};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+use tauri::async_runtime;
+
+use crate::rasterize::page_image_number;
+use crate::toolchain::Tool;
+use crate::transcription::page_xml_path;
+use crate::utility::run_utility;
+use crate::workspace::{load_workspace, page_number, save_workspace, PageStatus, Workspace};
+
+/// Pixels darker than this count as ink.
+const INK_THRESHOLD: u8 = 128;
+/// Share of the page ignored at each edge, where scanners leave shadows and punch holes.
+const MARGIN: f64 = 0.05;
+/// Pages with less ink than this are blank: dust and bleed-through stay below it, while a single
+/// line of text at the rendered size is above it.
+const BLANK_COVERAGE: f64 = 0.0005;
+/// Columns with more ink than this are bars of a patch code separator sheet.
+const BAR_COVERAGE: f64 = 0.8;
+/// Share of the columns that must be bars for a page to be a separator sheet.
+const SEPARATOR_BARS: f64 = 0.03;
+/// Tesseract orientation guesses below this confidence are ignored.
+const MIN_OSD_CONFIDENCE: f64 = 2.0;
+/// Text lines make the ink profile across them much more uneven than the profile along them.
+const SIDEWAYS_RATIO: f64 = 1.5;
+const UPRIGHT_JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InkAnalysis {
+    pub coverage: f64,
+    pub status: PageStatus,
+}
+
+/// Measures the ink on a grayscale page, leaving out the margins. A page is a separator when its
+/// ink is all in full-height bars, as on patch code sheets.
+pub fn analyze_ink(image: &GrayImage) -> InkAnalysis {
+    let (width, height) = image.dimensions();
+    let (margin_x, margin_y) = (
+        (width as f64 * MARGIN) as u32,
+        (height as f64 * MARGIN) as u32,
+    );
+    let (x_range, y_range) = (margin_x..width - margin_x, margin_y..height - margin_y);
+    let inner_height = y_range.len().max(1) as f64;
+
+    let mut ink = 0usize;
+    let mut bar_ink = 0usize;
+    let mut bars = 0usize;
+    for x in x_range.clone() {
+        let column_ink = y_range
+            .clone()
+            .filter(|y| image.get_pixel(x, *y).0[0] < INK_THRESHOLD)
+            .count();
+        ink += column_ink;
+        if column_ink as f64 / inner_height > BAR_COVERAGE {
+            bars += 1;
+            bar_ink += column_ink;
+        }
+    }
+
+    let area = (x_range.len() as f64 * inner_height).max(1.0);
+    let coverage = ink as f64 / area;
+    let status = if coverage < BLANK_COVERAGE {
+        PageStatus::Blank
+    } else if bars as f64 / x_range.len().max(1) as f64 >= SEPARATOR_BARS
+        && ((ink - bar_ink) as f64 / area) < BLANK_COVERAGE
+    {
+        PageStatus::Separator
+    } else {
+        PageStatus::Content
+    };
+
+    InkAnalysis { coverage, status }
+}
+
+fn analyze_page(path: &Path) -> Result<InkAnalysis, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?
+        .to_luma8();
+    Ok(analyze_ink(&image))
+}
+
+/// The `page-N.jpg` images of a scan, by page number.
+fn page_images(data_dir: &Path) -> Result<Vec<(u32, PathBuf)>, String> {
+    let mut images = Vec::new();
+    let entries = fs::read_dir(data_dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read directory: {}", e))?
+            .path();
+        if let Some(page) = page_image_number(&path) {
+            images.push((page, path));
+        }
+    }
+
+    images.sort();
+    Ok(images)
+}
+
+fn detect_pages(data_dir: &Path) -> Result<Workspace, String> {
+    let mut workspace = load_workspace(data_dir)?;
+
+    for (page, path) in page_images(data_dir)? {
+        let analysis = analyze_page(&path)?;
+        let state = workspace.pages.entry(page).or_default();
+        state.ink_coverage = Some(analysis.coverage);
+        if !state.manual {
+            state.status = analysis.status;
+        }
+    }
+
+    save_workspace(data_dir, &workspace)?;
+    Ok(workspace)
+}
+
+/// Finds the blank and separator pages among the `page-N.jpg` images of a scan and records them
+/// in its workspace as suggestions, which the user confirms with `set_page_status`. Runs locally,
+/// before any page is sent for transcription.
+#[tauri::command]
+pub async fn detect_blank_pages(data_path: String) -> Result<Workspace, String> {
+    async_runtime::spawn_blocking(move || detect_pages(Path::new(&data_path)))
+        .await
+        .map_err(|e| format!("Failed to analyze pages: {}", e))?
+}
+
+/// Overrides the detected status of a page.
+#[tauri::command]
+pub fn set_page_status(
+    data_path: String,
+    page: u32,
+    status: PageStatus,
+) -> Result<Workspace, String> {
+    let data_dir = Path::new(&data_path);
+    let mut workspace = load_workspace(data_dir)?;
+    let state = workspace.pages.entry(page).or_default();
+    state.status = status;
+    state.manual = true;
+    save_workspace(data_dir, &workspace)?;
+    Ok(workspace)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrientationMethod {
+    Tesseract,
+    Projection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrientationReport {
+    pub page: u32,
+    pub rotation: Option<u32>,
+    pub method: OrientationMethod,
+    /// The page looks sideways but its direction could not be told, so the user should rotate it.
+    pub needs_review: bool,
+}
+
+/// Asks tesseract's orientation and script detection for the clockwise rotation of a page.
+async fn tesseract_rotation(handle: &tauri::AppHandle, path: &Path) -> Option<u32> {
+    let output = run_utility(
+        handle,
+        Tool::Tesseract,
+        vec![
+            path.to_string_lossy().to_string(),
+            "stdout".to_string(),
+            "--psm".to_string(),
+            "0".to_string(),
+        ],
+        &path.to_string_lossy(),
+    )
+    .await
+    .ok()?;
+
+    let text = output.stdout;
+    let rotate = Regex::new(r"Rotate:\s*(\d+)").expect("Regex should never fail");
+    let confidence =
+        Regex::new(r"Orientation confidence:\s*([\d.]+)").expect("Regex should never fail");
+    let rotation: u32 = rotate.captures(&text)?[1].parse().ok()?;
+    let confidence: f64 = confidence.captures(&text)?[1].parse().ok()?;
+    (confidence >= MIN_OSD_CONFIDENCE && rotation % 90 == 0).then_some(rotation % 360)
+}
+
+fn profile_variance(profile: &[usize]) -> f64 {
+    let mean = profile.iter().sum::<usize>() as f64 / profile.len().max(1) as f64;
+    profile
+        .iter()
+        .map(|value| (*value as f64 - mean).powi(2))
+        .sum::<f64>()
+        / profile.len().max(1) as f64
+}
+
+/// Tells from the ink projection profiles whether the text lines run vertically. It cannot tell
+/// 90 from 270 degrees, nor upright from upside down.
+pub fn is_sideways(image: &GrayImage) -> bool {
+    let (width, height) = image.dimensions();
+    let mut rows = vec![0usize; height as usize];
+    let mut columns = vec![0usize; width as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[0] < INK_THRESHOLD {
+            rows[y as usize] += 1;
+            columns[x as usize] += 1;
+        }
+    }
+
+    // Normalize by the profile length, so that the page format does not weigh in.
+    let row_variance = profile_variance(&rows) / (width as f64).powi(2);
+    let column_variance = profile_variance(&columns) / (height as f64).powi(2);
+    column_variance > row_variance * SIDEWAYS_RATIO
+}
+
+async fn detect_orientation(
+    handle: &tauri::AppHandle,
+    page: u32,
+    path: PathBuf,
+) -> Result<OrientationReport, String> {
+    if let Some(rotation) = tesseract_rotation(handle, &path).await {
+        return Ok(OrientationReport {
+            page,
+            rotation: Some(rotation),
+            method: OrientationMethod::Tesseract,
+            needs_review: false,
+        });
+    }
+
+    let sideways = async_runtime::spawn_blocking(move || {
+        image::open(&path)
+            .map(|image| is_sideways(&image.to_luma8()))
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+    })
+    .await
+    .map_err(|e| format!("Failed to analyze page: {}", e))??;
+
+    Ok(OrientationReport {
+        page,
+        rotation: (!sideways).then_some(0),
+        method: OrientationMethod::Projection,
+        needs_review: sideways,
+    })
+}
+
+/// Detects the orientation of the content pages of a scan that have no rotation yet, with
+/// tesseract when it is installed and from the ink projection profiles otherwise.
+#[tauri::command]
+pub async fn detect_page_orientation(
+    handle: tauri::AppHandle,
+    data_path: String,
+) -> Result<Vec<OrientationReport>, String> {
+    let data_dir = Path::new(&data_path);
+    let mut workspace = load_workspace(data_dir)?;
+    let mut reports = Vec::new();
+
+    for (page, path) in page_images(data_dir)? {
+        let state = workspace.pages.get(&page);
+        if workspace.is_skipped(page) || state.is_some_and(|state| state.rotation.is_some()) {
+            continue;
+        }
+
+        let report = detect_orientation(&handle, page, path.clone()).await?;
+        if report.rotation.unwrap_or(0) != workspace.rotation(page) {
+            forget_transcription(&path)?;
+        }
+        workspace.pages.entry(page).or_default().rotation = report.rotation;
+        reports.push(report);
+    }
+
+    save_workspace(data_dir, &workspace)?;
+    Ok(reports)
+}
+
+/// Removes the cached transcription of a page image, which was read in its previous orientation.
+fn forget_transcription(image_path: &Path) -> Result<(), String> {
+    let xml_path = page_xml_path(image_path);
+    if xml_path.exists() {
+        fs::remove_file(&xml_path)
+            .map_err(|e| format!("Failed to remove {}: {}", xml_path.display(), e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_page_rotation(data_path: String, page: u32, rotation: u32) -> Result<Workspace, String> {
+    if rotation % 90 != 0 || rotation >= 360 {
+        return Err(format!("Invalid rotation: {}", rotation));
+    }
+
+    let data_dir = Path::new(&data_path);
+    let mut workspace = load_workspace(data_dir)?;
+    if rotation != workspace.rotation(page) {
+        forget_transcription(&data_dir.join(format!("page-{}.jpg", page)))?;
+    }
+    workspace.pages.entry(page).or_default().rotation = Some(rotation);
+    save_workspace(data_dir, &workspace)?;
+    Ok(workspace)
+}
+
+/// Path of the page image to transcribe: the image itself when it is upright, otherwise a rotated
+/// copy cached next to it.
+pub fn upright_image_path(path: &str) -> Result<String, String> {
+    let image_path = Path::new(path);
+    let (Some(data_dir), Some(page)) = (image_path.parent(), page_number(path)) else {
+        return Ok(path.to_string());
+    };
+    let rotation = load_workspace(data_dir)?.rotation(page);
+    if rotation == 0 {
+        return Ok(path.to_string());
+    }
+
+    let upright_path = data_dir.join(format!("page-{}-upright-{}.jpg", page, rotation));
+    if !upright_path.exists() {
+        let image =
+            image::open(image_path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let upright = match rotation {
+            90 => image.rotate90(),
+            180 => image.rotate180(),
+            _ => image.rotate270(),
+        };
+        let file =
+            File::create(&upright_path).map_err(|e| format!("Failed to create file: {}", e))?;
+        DynamicImage::ImageRgb8(upright.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(file, UPRIGHT_JPEG_QUALITY))
+            .map_err(|e| format!("Failed to write upright image: {}", e))?;
+    }
+
+    Ok(upright_path.to_string_lossy().to_string())
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Local};
+use lopdf::Document;
+use std::path::Path;
+
+use crate::llm::models::DocumentInfo;
+use crate::settings::OutputProfile;
+use attachments::{embed_files, evidence};
+use metadata::{write_metadata, PdfMetadata};
+use update::PdfUpdate;
+
+pub mod attachments;
+pub mod metadata;
+pub mod pages;
+pub mod text;
+pub mod update;
+
+pub fn load_pdf(path: &Path) -> Result<Document, String> {
+    Document::load(path).map_err(|e| format!("Failed to read PDF {}: {}", path.display(), e))
+}
+
+pub fn save_pdf(document: &mut Document, path: &Path) -> Result<(), String> {
+    document
+        .save(path)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write PDF {}: {}", path.display(), e))
+}
+
+/// Date in the `D:YYYYMMDDHHmmSS+HH'mm'` format of PDF date strings.
+pub fn pdf_date(date: &DateTime<Local>) -> String {
+    let offset = date.format("%z").to_string();
+    format!(
+        "D:{}{}'{}'",
+        date.format("%Y%m%d%H%M%S"),
+        &offset[..3],
+        &offset[3..]
+    )
+}
+
+/// Embeds the metadata of a document into its finished PDF and, for PDF/A-3, its evidence
+/// files, in a single incremental update.
+pub fn embed_document_info(
+    path: &Path,
+    document_info: &DocumentInfo,
+    output_profile: OutputProfile,
+) -> Result<(), String> {
+    let mut update = PdfUpdate::load(path)?;
+    write_metadata(
+        &mut update.document,
+        &PdfMetadata::from_document(document_info),
+    )?;
+    if output_profile == OutputProfile::Pdfa3 {
+        embed_files(&mut update.document, &evidence(document_info)?)?;
+    }
+    update.save(path)
+}
@@ -0,0 +1,185 @@
+use chrono::Local;
+use lopdf::{text_string, Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use std::{fs, path::Path};
+
+use super::pdf_date;
+use crate::llm::models::DocumentInfo;
+use crate::transcription::xml_path_for;
+
+/// How an associated file relates to the PDF, as the `AFRelationship` of PDF/A-3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    /// The file the content of the PDF was produced from.
+    Source,
+    /// Data extracted from or represented by the content of the PDF.
+    Data,
+}
+
+impl Relationship {
+    fn name(&self) -> &'static [u8] {
+        match self {
+            Relationship::Source => b"Source",
+            Relationship::Data => b"Data",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub file_name: String,
+    pub description: String,
+    pub mime_type: String,
+    pub relationship: Relationship,
+    pub content: Vec<u8>,
+}
+
+fn add_file_specification(document: &mut Document, attachment: &Attachment) -> ObjectId {
+    let mut params = Dictionary::new();
+    params.set("Size", Object::Integer(attachment.content.len() as i64));
+    params.set("ModDate", Object::string_literal(pdf_date(&Local::now())));
+
+    let mut stream_dictionary = Dictionary::new();
+    stream_dictionary.set("Type", Object::Name(b"EmbeddedFile".to_vec()));
+    stream_dictionary.set(
+        "Subtype",
+        Object::Name(attachment.mime_type.as_bytes().to_vec()),
+    );
+    stream_dictionary.set("Params", Object::Dictionary(params));
+    let stream_id = document.add_object(Stream::new(stream_dictionary, attachment.content.clone()));
+
+    let mut embedded_files = Dictionary::new();
+    embedded_files.set("F", Object::Reference(stream_id));
+    embedded_files.set("UF", Object::Reference(stream_id));
+
+    let mut specification = Dictionary::new();
+    specification.set("Type", Object::Name(b"Filespec".to_vec()));
+    specification.set(
+        "F",
+        Object::String(
+            attachment.file_name.as_bytes().to_vec(),
+            StringFormat::Literal,
+        ),
+    );
+    specification.set("UF", text_string(&attachment.file_name));
+    specification.set("Desc", text_string(&attachment.description));
+    specification.set(
+        "AFRelationship",
+        Object::Name(attachment.relationship.name().to_vec()),
+    );
+    specification.set("EF", Object::Dictionary(embedded_files));
+    document.add_object(specification)
+}
+
+/// Embeds files as PDF/A-3 associated files of the document: listed in the `EmbeddedFiles` name
+/// tree, so that viewers show them, and in the `AF` array of the catalog.
+pub fn embed_files(document: &mut Document, attachments: &[Attachment]) -> Result<(), String> {
+    let specifications: Vec<(String, ObjectId)> = attachments
+        .iter()
+        .map(|attachment| {
+            (
+                attachment.file_name.clone(),
+                add_file_specification(document, attachment),
+            )
+        })
+        .collect();
+
+    // Files embedded earlier under other names are kept; ours replace those with the same name.
+    let mut entries: Vec<(Vec<u8>, Object)> = Vec::new();
+    let existing = document
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get_deref(b"Names", document).ok())
+        .and_then(|names| names.as_dict().ok())
+        .and_then(|names| names.get_deref(b"EmbeddedFiles", document).ok())
+        .and_then(|tree| tree.as_dict().ok())
+        .and_then(|tree| tree.get(b"Names").ok())
+        .and_then(|names| names.as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    for pair in existing.chunks(2) {
+        if let [Object::String(name, _), specification] = pair {
+            if !specifications
+                .iter()
+                .any(|(file_name, _)| file_name.as_bytes() == name.as_slice())
+            {
+                entries.push((name.clone(), specification.clone()));
+            }
+        }
+    }
+    for (file_name, id) in &specifications {
+        entries.push((file_name.as_bytes().to_vec(), Object::Reference(*id)));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut tree = Dictionary::new();
+    tree.set(
+        "Names",
+        Object::Array(
+            entries
+                .into_iter()
+                .flat_map(|(name, specification)| {
+                    [Object::String(name, StringFormat::Literal), specification]
+                })
+                .collect(),
+        ),
+    );
+    let tree_id = document.add_object(tree);
+
+    let mut names = document
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get_deref(b"Names", document).ok())
+        .and_then(|names| names.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    names.set("EmbeddedFiles", Object::Reference(tree_id));
+
+    let mut associated_files = document
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get_deref(b"AF", document).ok())
+        .and_then(|files| files.as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    associated_files.extend(specifications.iter().map(|(_, id)| Object::Reference(*id)));
+
+    let catalog = document
+        .catalog_mut()
+        .map_err(|e| format!("Failed to read PDF catalog: {}", e))?;
+    catalog.set("Names", Object::Dictionary(names));
+    catalog.set("AF", Object::Array(associated_files));
+    Ok(())
+}
+
+/// The transcription the document was produced from and the data extracted from it, embedded
+/// into PDF/A-3 files so that they carry their own evidence.
+pub fn evidence(document_info: &DocumentInfo) -> Result<Vec<Attachment>, String> {
+    let mut attachments = Vec::new();
+
+    let xml_path = xml_path_for(Path::new(&document_info.json_file_path));
+    match fs::read(&xml_path) {
+        Ok(content) => attachments.push(Attachment {
+            file_name: "transcription.xml".to_string(),
+            description: "Transcrição das páginas do documento".to_string(),
+            mime_type: "application/xml".to_string(),
+            relationship: Relationship::Source,
+            content,
+        }),
+        Err(e) => println!(
+            "Transcription not embedded, failed to read {}: {}",
+            xml_path.display(),
+            e
+        ),
+    }
+
+    let content = serde_json::to_vec_pretty(document_info)
+        .map_err(|e| format!("Failed to serialize document info: {}", e))?;
+    attachments.push(Attachment {
+        file_name: "document.json".to_string(),
+        description: "Dados extraídos do documento".to_string(),
+        mime_type: "application/json".to_string(),
+        relationship: Relationship::Data,
+        content,
+    });
+    Ok(attachments)
+}
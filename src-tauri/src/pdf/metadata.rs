@@ -0,0 +1,281 @@
+use chrono::{Local, NaiveDate, SecondsFormat};
+use lopdf::{decode_text_string, text_string, Dictionary, Document, Object, Stream};
+use regex::Regex;
+
+use super::pdf_date;
+use crate::entities::{is_valid_cnpj, split_entity_names};
+use crate::llm::models::DocumentInfo;
+use crate::text::digits_only;
+
+const CREATOR_TOOL: &str = "auxiliar-de-cadastros";
+/// Namespace of the custom XMP properties, declared through a PDF/A extension schema.
+const NAMESPACE: &str = "http://ns.auxiliar-de-cadastros.app/document/1.0/";
+const PREFIX: &str = "cad";
+
+/// Custom properties with their XMP value type and description, as required by PDF/A.
+const CUSTOM_PROPERTIES: &[(&str, &str, &str)] = &[
+    ("DocumentType", "Text", "Canonical document type name"),
+    (
+        "DocumentTypeId",
+        "Text",
+        "Canonical document type identifier",
+    ),
+    (
+        "Cnpj",
+        "Text",
+        "CNPJ of the main company in the document, digits only",
+    ),
+    (
+        "DocumentDate",
+        "Date",
+        "Most important date of the document",
+    ),
+];
+
+/// What the finished PDF carries about its document, in the Info dictionary and in XMP.
+#[derive(Debug, Clone, Default)]
+pub struct PdfMetadata {
+    pub title: String,
+    pub subject: String,
+    pub keywords: Vec<String>,
+    pub document_type: String,
+    pub document_type_id: String,
+    pub cnpj: Option<String>,
+    pub document_date: Option<NaiveDate>,
+}
+
+impl PdfMetadata {
+    pub fn from_document(document_info: &DocumentInfo) -> Self {
+        let reasoning = &document_info.reasoning;
+        let (document_type, document_type_abbr, document_type_id) =
+            match &document_info.canonical_type {
+                Some(canonical) => (
+                    canonical.type_name.clone(),
+                    canonical.type_abbr.clone(),
+                    canonical.id.clone(),
+                ),
+                None => (
+                    reasoning.document_type.type_name.clone(),
+                    reasoning.type_abbreviation.type_abbr.clone(),
+                    String::new(),
+                ),
+            };
+
+        let entities = if document_info.resolved_entities.is_empty() {
+            split_entity_names(&reasoning.main_entities.entities)
+        } else {
+            document_info
+                .resolved_entities
+                .iter()
+                .map(|entity| entity.canonical_name.clone())
+                .collect()
+        };
+
+        let mut keywords = vec![document_type.clone(), document_type_abbr];
+        keywords.extend(entities);
+        keywords.retain(|keyword| !keyword.trim().is_empty());
+        keywords.dedup();
+
+        let cnpj = std::iter::once(digits_only(&reasoning.identifiers.cnpj))
+            .chain(
+                document_info
+                    .resolved_entities
+                    .iter()
+                    .map(|entity| entity.id.clone()),
+            )
+            .find(|id| is_valid_cnpj(id));
+
+        Self {
+            title: document_info.file_name.clone(),
+            subject: reasoning.document_summary.summary.clone(),
+            keywords,
+            document_type,
+            document_type_id,
+            cnpj,
+            document_date: reasoning.important_date.parse(),
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// PDF/A part and conformance level declared in the existing XMP packet, defaulting to 2B.
+fn pdfa_identification(xmp: &str) -> (String, String) {
+    let part = Regex::new(r#"pdfaid:part(?:="|>)(\d)"#).expect("Regex should never fail");
+    let conformance =
+        Regex::new(r#"pdfaid:conformance(?:="|>)([ABU])"#).expect("Regex should never fail");
+    (
+        part.captures(xmp)
+            .map_or("2".to_string(), |captures| captures[1].to_string()),
+        conformance
+            .captures(xmp)
+            .map_or("B".to_string(), |captures| captures[1].to_string()),
+    )
+}
+
+fn extension_schema() -> String {
+    let properties: String = CUSTOM_PROPERTIES
+        .iter()
+        .map(|(name, value_type, description)| {
+            format!(
+                "<rdf:li rdf:parseType=\"Resource\">\
+                 <pdfaProperty:name>{}</pdfaProperty:name>\
+                 <pdfaProperty:valueType>{}</pdfaProperty:valueType>\
+                 <pdfaProperty:category>external</pdfaProperty:category>\
+                 <pdfaProperty:description>{}</pdfaProperty:description>\
+                 </rdf:li>\n",
+                name, value_type, description
+            )
+        })
+        .collect();
+
+    format!(
+        "<pdfaExtension:schemas><rdf:Bag><rdf:li rdf:parseType=\"Resource\">\n\
+         <pdfaSchema:schema>Auxiliar de Cadastros document metadata</pdfaSchema:schema>\n\
+         <pdfaSchema:namespaceURI>{}</pdfaSchema:namespaceURI>\n\
+         <pdfaSchema:prefix>{}</pdfaSchema:prefix>\n\
+         <pdfaSchema:property><rdf:Seq>\n{}</rdf:Seq></pdfaSchema:property>\n\
+         </rdf:li></rdf:Bag></pdfaExtension:schemas>",
+        NAMESPACE, PREFIX, properties
+    )
+}
+
+/// Builds the XMP packet. Every Info entry is mirrored by its XMP equivalent, as PDF/A requires.
+fn xmp_packet(
+    metadata: &PdfMetadata,
+    pdfa: &(String, String),
+    producer: &str,
+    date: &str,
+) -> String {
+    let mut custom = vec![
+        ("DocumentType", metadata.document_type.clone()),
+        ("DocumentTypeId", metadata.document_type_id.clone()),
+    ];
+    if let Some(cnpj) = &metadata.cnpj {
+        custom.push(("Cnpj", cnpj.clone()));
+    }
+    if let Some(document_date) = metadata.document_date {
+        custom.push(("DocumentDate", document_date.format("%Y-%m-%d").to_string()));
+    }
+    let custom: String = custom
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| format!("<{0}:{1}>{2}</{0}:{1}>\n", PREFIX, name, escape_xml(value)))
+        .collect();
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         <rdf:Description rdf:about=\"\" \
+         xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" \
+         xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+         xmlns:pdfaExtension=\"http://www.aiim.org/pdfa/ns/extension/\" \
+         xmlns:pdfaSchema=\"http://www.aiim.org/pdfa/ns/schema#\" \
+         xmlns:pdfaProperty=\"http://www.aiim.org/pdfa/ns/property#\" \
+         xmlns:{prefix}=\"{namespace}\">\n\
+         <pdfaid:part>{part}</pdfaid:part>\n\
+         <pdfaid:conformance>{conformance}</pdfaid:conformance>\n\
+         <dc:format>application/pdf</dc:format>\n\
+         <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{title}</rdf:li></rdf:Alt></dc:title>\n\
+         <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{subject}</rdf:li></rdf:Alt></dc:description>\n\
+         <pdf:Keywords>{keywords}</pdf:Keywords>\n\
+         <pdf:Producer>{producer}</pdf:Producer>\n\
+         <xmp:CreatorTool>{creator}</xmp:CreatorTool>\n\
+         <xmp:CreateDate>{date}</xmp:CreateDate>\n\
+         <xmp:ModifyDate>{date}</xmp:ModifyDate>\n\
+         <xmp:MetadataDate>{date}</xmp:MetadataDate>\n\
+         {custom}{extension}\n\
+         </rdf:Description>\n\
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>",
+        prefix = PREFIX,
+        namespace = NAMESPACE,
+        part = pdfa.0,
+        conformance = pdfa.1,
+        title = escape_xml(&metadata.title),
+        subject = escape_xml(&metadata.subject),
+        keywords = escape_xml(&metadata.keywords.join("; ")),
+        producer = escape_xml(producer),
+        creator = CREATOR_TOOL,
+        date = date,
+        custom = custom,
+        extension = extension_schema(),
+    )
+}
+
+fn existing_xmp(document: &Document) -> Option<String> {
+    let metadata = document
+        .catalog()
+        .ok()?
+        .get_deref(b"Metadata", document)
+        .ok()?;
+    let stream = metadata.as_stream().ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    Some(String::from_utf8_lossy(&content).to_string())
+}
+
+/// Info dictionary of the document, created and linked from the trailer when missing.
+fn info_dictionary(document: &mut Document) -> Result<&mut Dictionary, String> {
+    let info_id = match document.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(id) => id,
+        Err(_) => {
+            let id = document.add_object(Dictionary::new());
+            document.trailer.set("Info", Object::Reference(id));
+            id
+        }
+    };
+    document
+        .get_dictionary_mut(info_id)
+        .map_err(|e| format!("Failed to read PDF Info dictionary: {}", e))
+}
+
+/// Writes the Info dictionary and a matching XMP packet, keeping the PDF/A identification and
+/// the producer of the existing metadata.
+pub fn write_metadata(document: &mut Document, metadata: &PdfMetadata) -> Result<(), String> {
+    let pdfa = pdfa_identification(&existing_xmp(document).unwrap_or_default());
+    let now = Local::now();
+
+    let info = info_dictionary(document)?;
+    let producer = info
+        .get(b"Producer")
+        .ok()
+        .and_then(|producer| decode_text_string(producer).ok())
+        .unwrap_or_else(|| CREATOR_TOOL.to_string());
+    info.set("Title", text_string(&metadata.title));
+    info.set("Subject", text_string(&metadata.subject));
+    info.set("Keywords", text_string(&metadata.keywords.join("; ")));
+    info.set("Creator", text_string(CREATOR_TOOL));
+    info.set("Producer", text_string(&producer));
+    info.set("CreationDate", Object::string_literal(pdf_date(&now)));
+    info.set("ModDate", Object::string_literal(pdf_date(&now)));
+
+    let xmp = xmp_packet(
+        metadata,
+        &pdfa,
+        &producer,
+        &now.to_rfc3339_opts(SecondsFormat::Secs, false),
+    );
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Type", Object::Name(b"Metadata".to_vec()));
+    dictionary.set("Subtype", Object::Name(b"XML".to_vec()));
+    // PDF/A forbids filters on the metadata stream.
+    let stream = Stream::new(dictionary, xmp.into_bytes()).with_compression(false);
+    let metadata_id = document.add_object(stream);
+    document
+        .catalog_mut()
+        .map_err(|e| format!("Failed to read PDF catalog: {}", e))?
+        .set("Metadata", Object::Reference(metadata_id));
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use super::{load_pdf, save_pdf};
+
+/// Attributes a page can inherit from its ancestors in the page tree.
+const INHERITABLE_KEYS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+/// Catalog entries that point at pages of the original and would dangle in a subset.
+const PAGE_REFERENCING_KEYS: [&[u8]; 4] = [b"Outlines", b"Dests", b"StructTreeRoot", b"PageLabels"];
+
+/// Consecutive pages of the original, 1-based and inclusive, copied with the same clockwise
+/// rotation. A range whose end is before its start is copied in reverse order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRange {
+    pub start: u32,
+    pub end: u32,
+    pub rotation: u32,
+}
+
+impl PageRange {
+    pub fn pages(&self) -> Box<dyn Iterator<Item = u32>> {
+        if self.start <= self.end {
+            Box::new(self.start..=self.end)
+        } else {
+            Box::new((self.end..=self.start).rev())
+        }
+    }
+}
+
+impl fmt::Display for PageRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)?;
+        } else {
+            write!(f, "{}-{}", self.start, self.end)?;
+        }
+        if self.rotation != 0 {
+            write!(f, "@{}", self.rotation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups pages, in output order, into the fewest ranges that keep their order and rotation.
+pub fn page_ranges(pages: &[(u32, u32)]) -> Vec<PageRange> {
+    let mut ranges: Vec<PageRange> = Vec::new();
+    for &(page, rotation) in pages {
+        if let Some(last) = ranges.last_mut() {
+            let ascending = last.start <= last.end && page == last.end + 1;
+            let descending = last.start >= last.end && page + 1 == last.end;
+            if last.rotation == rotation && (ascending || descending) {
+                last.end = page;
+                continue;
+            }
+        }
+        ranges.push(PageRange {
+            start: page,
+            end: page,
+            rotation,
+        });
+    }
+    ranges
+}
+
+/// Value of an attribute of a page, looked up through its ancestors when it is inherited.
+fn inherited(document: &Document, page: &Dictionary, key: &[u8]) -> Option<Object> {
+    if let Ok(value) = page.get(key) {
+        return Some(value.clone());
+    }
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    while let Some(id) = parent {
+        let node = document.get_dictionary(id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    None
+}
+
+/// Longest side of each page, in points, from its crop box or else its media box.
+pub fn page_long_sides(document: &Document) -> BTreeMap<u32, f32> {
+    document
+        .get_pages()
+        .into_iter()
+        .filter_map(|(page, page_id)| {
+            let dictionary = document.get_dictionary(page_id).ok()?;
+            let bounds = inherited(document, dictionary, b"CropBox")
+                .or_else(|| inherited(document, dictionary, b"MediaBox"))?;
+            let bounds = match bounds {
+                Object::Reference(id) => document.get_object(id).ok()?.clone(),
+                bounds => bounds,
+            };
+            let values: Vec<f32> = bounds
+                .as_array()
+                .ok()?
+                .iter()
+                .filter_map(|value| value.as_float().ok())
+                .collect();
+            let [x1, y1, x2, y2] = values[..] else {
+                return None;
+            };
+            Some((page, (x2 - x1).abs().max((y2 - y1).abs())))
+        })
+        .collect()
+}
+
+/// Copy of a page attached to a new parent, with its inherited attributes made explicit and the
+/// extra rotation added to its own.
+fn page_copy(
+    document: &Document,
+    page_id: ObjectId,
+    parent_id: ObjectId,
+    rotation: u32,
+) -> Result<Dictionary, String> {
+    let mut page = document
+        .get_dictionary(page_id)
+        .map_err(|e| format!("Failed to read page object {:?}: {}", page_id, e))?
+        .clone();
+    for key in INHERITABLE_KEYS {
+        if let Some(value) = inherited(document, &page, key) {
+            page.set(key, value);
+        }
+    }
+
+    let current = page
+        .get(b"Rotate")
+        .and_then(Object::as_i64)
+        .unwrap_or(0)
+        .rem_euclid(360);
+    page.set(
+        "Rotate",
+        Object::Integer((current + rotation as i64).rem_euclid(360)),
+    );
+    page.set("Parent", Object::Reference(parent_id));
+    Ok(page)
+}
+
+/// Replaces the pages of a document with the given ranges of its own pages. Pages that are
+/// selected more than once are copied, since a page object can only have one parent.
+pub fn select_pages(document: &mut Document, ranges: &[PageRange]) -> Result<(), String> {
+    let original_pages = document.get_pages();
+    let selected: Vec<(u32, u32)> = ranges
+        .iter()
+        .flat_map(|range| range.pages().map(|page| (page, range.rotation)))
+        .collect();
+    if selected.is_empty() {
+        return Err("No pages selected".to_string());
+    }
+
+    let pages_id = document.new_object_id();
+    let mut kids = Vec::new();
+    for (page, rotation) in selected {
+        let page_id = *original_pages.get(&page).ok_or_else(|| {
+            format!(
+                "Page {} does not exist, the PDF has {} pages",
+                page,
+                original_pages.len()
+            )
+        })?;
+        let copy = page_copy(document, page_id, pages_id, rotation)?;
+        kids.push(Object::Reference(document.add_object(copy)));
+    }
+
+    let mut pages = Dictionary::new();
+    pages.set("Type", Object::Name(b"Pages".to_vec()));
+    pages.set("Count", Object::Integer(kids.len() as i64));
+    pages.set("Kids", Object::Array(kids));
+    document.objects.insert(pages_id, Object::Dictionary(pages));
+
+    let catalog = document
+        .catalog_mut()
+        .map_err(|e| format!("Failed to read PDF catalog: {}", e))?;
+    catalog.set("Pages", Object::Reference(pages_id));
+    for key in PAGE_REFERENCING_KEYS {
+        catalog.remove(key);
+    }
+
+    // The original pages, and whatever only they used, are no longer reachable.
+    document.prune_objects();
+    document.renumber_objects();
+    document.compress();
+    Ok(())
+}
+
+/// Writes the given ranges of pages of a PDF, in the given order and rotation, to a new file.
+pub fn extract_pages(
+    source: &Path,
+    ranges: &[PageRange],
+    destination: &Path,
+) -> Result<(), String> {
+    let mut document = load_pdf(source)?;
+    select_pages(&mut document, ranges)?;
+    save_pdf(&mut document, destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    fn range(start: u32, end: u32, rotation: u32) -> PageRange {
+        PageRange {
+            start,
+            end,
+            rotation,
+        }
+    }
+
+    #[test]
+    fn groups_consecutive_pages_with_the_same_rotation() {
+        assert_eq!(
+            page_ranges(&[(1, 0), (2, 0), (3, 0), (5, 0), (6, 90), (7, 90)]),
+            vec![range(1, 3, 0), range(5, 5, 0), range(6, 7, 90)]
+        );
+        assert_eq!(
+            page_ranges(&[(4, 0), (3, 0), (2, 0), (2, 0)]),
+            vec![range(4, 2, 0), range(2, 2, 0)]
+        );
+        assert!(page_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn lists_and_displays_ranges() {
+        assert_eq!(range(4, 2, 0).pages().collect::<Vec<_>>(), vec![4, 3, 2]);
+        assert_eq!(range(2, 4, 0).to_string(), "2-4");
+        assert_eq!(range(3, 3, 180).to_string(), "3@180");
+    }
+
+    /// A document whose pages inherit their media box and rotation, each page as wide as 100
+    /// points times its number so that it can be told apart after renumbering.
+    fn sample_document(page_count: u32) -> Document {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let kids: Vec<Object> = (1..=page_count)
+            .map(|page| {
+                let page_id = document.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "CropBox" => vec![0.into(), 0.into(), (page * 100).into(), 800.into()],
+                });
+                page_id.into()
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => page_count,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                "Rotate" => 90,
+            }),
+        );
+        let outlines_id = document.add_object(dictionary! { "Type" => "Outlines" });
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        document
+    }
+
+    fn page_attributes(document: &Document) -> Vec<(f32, i64, bool)> {
+        document
+            .get_pages()
+            .into_values()
+            .map(|page_id| {
+                let page = document.get_dictionary(page_id).unwrap();
+                let width = page.get(b"CropBox").unwrap().as_array().unwrap()[2]
+                    .as_float()
+                    .unwrap();
+                let rotation = page.get(b"Rotate").unwrap().as_i64().unwrap();
+                (width, rotation, page.has(b"MediaBox"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_pages_in_order_with_their_rotation() {
+        let mut document = sample_document(4);
+        let ranges = page_ranges(&[(3, 0), (2, 0), (4, 90), (2, 270)]);
+        select_pages(&mut document, &ranges).unwrap();
+
+        assert_eq!(
+            page_attributes(&document),
+            vec![
+                (300.0, 90, true),
+                (200.0, 90, true),
+                (400.0, 180, true),
+                (200.0, 0, true),
+            ]
+        );
+        assert!(!document.catalog().unwrap().has(b"Outlines"));
+    }
+
+    #[test]
+    fn rejects_missing_pages() {
+        let mut document = sample_document(2);
+        assert!(select_pages(&mut document, &[range(2, 3, 0)]).is_err());
+        assert!(select_pages(&mut document, &[]).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use lopdf::{content::Content, Dictionary, Document, Object, ObjectId};
+
+/// How deep form XObjects are followed, in case a malformed file nests them in a cycle.
+const MAX_FORM_DEPTH: usize = 8;
+/// How far up the page tree inherited resources are looked for.
+const MAX_TREE_DEPTH: usize = 32;
+/// Operators that show text.
+const TEXT_OPERATORS: &[&str] = &["Tj", "TJ", "'", "\""];
+
+/// Resolves a dictionary that may be stored directly or through a reference.
+fn dictionary<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    match object {
+        Object::Reference(id) => document.get_dictionary(*id).ok(),
+        Object::Dictionary(dictionary) => Some(dictionary),
+        _ => None,
+    }
+}
+
+fn shows_text(operands: &[Object]) -> bool {
+    operands.iter().any(|operand| match operand {
+        Object::String(bytes, _) => !bytes.is_empty(),
+        Object::Array(items) => shows_text(items),
+        _ => false,
+    })
+}
+
+/// Whether a content stream, or a form XObject it paints, shows any text. Text drawn invisibly,
+/// as in OCR layers, counts.
+fn has_text(document: &Document, content: &[u8], resources: &[&Dictionary], depth: usize) -> bool {
+    let Ok(content) = Content::decode(content) else {
+        return false;
+    };
+    content.operations.iter().any(|operation| {
+        if TEXT_OPERATORS.contains(&operation.operator.as_str()) {
+            return shows_text(&operation.operands);
+        }
+        if operation.operator != "Do" || depth >= MAX_FORM_DEPTH {
+            return false;
+        }
+        let Some(Ok(name)) = operation.operands.first().map(Object::as_name) else {
+            return false;
+        };
+        let form = resources.iter().find_map(|resources| {
+            let xobjects = dictionary(document, resources.get(b"XObject").ok()?)?;
+            let id = xobjects.get(name).and_then(Object::as_reference).ok()?;
+            document.get_object(id).and_then(Object::as_stream).ok()
+        });
+        let Some(form) = form.filter(|form| {
+            form.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form".as_slice())
+        }) else {
+            return false;
+        };
+        let Ok(form_content) = form.get_plain_content() else {
+            return false;
+        };
+        // Forms without resources of their own use those of the page.
+        let mut form_resources: Vec<&Dictionary> = form
+            .dict
+            .get(b"Resources")
+            .ok()
+            .and_then(|object| dictionary(document, object))
+            .into_iter()
+            .collect();
+        form_resources.extend(resources);
+        has_text(document, &form_content, &form_resources, depth + 1)
+    })
+}
+
+/// Resource dictionaries of a page, its own first, then those inherited from the page tree.
+fn page_resources(document: &Document, page_id: ObjectId) -> Vec<&Dictionary> {
+    let mut resources = Vec::new();
+    let mut node = document.get_dictionary(page_id).ok();
+    for _ in 0..MAX_TREE_DEPTH {
+        let Some(page_node) = node else {
+            break;
+        };
+        resources.extend(
+            page_node
+                .get(b"Resources")
+                .ok()
+                .and_then(|object| dictionary(document, object)),
+        );
+        node = page_node
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| document.get_dictionary(id))
+            .ok();
+    }
+    resources
+}
+
+/// Pages, numbered from 1, that have no text at all.
+pub fn pages_without_text(document: &Document) -> Vec<u32> {
+    document
+        .get_pages()
+        .into_iter()
+        .filter(|(_, page_id)| {
+            let Ok(content) = document.get_page_content(*page_id) else {
+                return true;
+            };
+            let resources = page_resources(document, *page_id);
+            !has_text(document, &content, &resources, 0)
+        })
+        .map(|(page, _)| page)
+        .collect()
+}
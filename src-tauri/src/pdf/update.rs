@@ -0,0 +1,172 @@
+use lopdf::{Document, IncrementalDocument};
+use std::{fs, path::Path};
+
+/// Trailer entries of the previous cross-reference section that do not apply to the appended
+/// one: lopdf writes the new section unfiltered, and hybrid files point to their own stream.
+const STALE_TRAILER_KEYS: &[&[u8]] = &[b"DecodeParms", b"Filter", b"XRefStm"];
+/// Trailer entries that edits may change.
+const EDITED_TRAILER_KEYS: &[&[u8]] = &[b"Root", b"Info"];
+
+/// A PDF changed through an incremental update: edits are made to `document` and only the
+/// objects that differ from the file are appended to it. The original bytes stay as they are,
+/// header and binary comment line included, so a PDF/A file produced by ocrmypdf stays one.
+pub struct PdfUpdate {
+    bytes: Vec<u8>,
+    original: Document,
+    pub document: Document,
+}
+
+impl PdfUpdate {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to read PDF {}: {}", path.display(), e))?;
+        Self::from_bytes(bytes).map_err(|e| format!("Failed to read PDF {}: {}", path.display(), e))
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        let original = Document::load_mem(&bytes).map_err(|e| e.to_string())?;
+        Ok(Self {
+            bytes,
+            document: original.clone(),
+            original,
+        })
+    }
+
+    /// The file with the changes appended.
+    fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut update =
+            IncrementalDocument::create_from(self.bytes.clone(), self.original.clone());
+        let new_document = &mut update.new_document;
+        for (id, object) in &self.document.objects {
+            if self.original.objects.get(id) != Some(object) {
+                new_document.objects.insert(*id, object.clone());
+            }
+        }
+        new_document.max_id = self.document.max_id;
+        for key in STALE_TRAILER_KEYS {
+            new_document.trailer.remove(key);
+        }
+        for key in EDITED_TRAILER_KEYS {
+            if let Ok(value) = self.document.trailer.get(key) {
+                new_document.trailer.set(key.to_vec(), value.clone());
+            }
+        }
+
+        let mut output = Vec::with_capacity(self.bytes.len());
+        update
+            .save_to(&mut output)
+            .map_err(|e| format!("Failed to write PDF update: {}", e))?;
+
+        // lopdf starts the appended section with another `%PDF-` header line. It is a comment
+        // there, but is blanked out so that the file keeps a single header.
+        let start = self.bytes.len() + usize::from(self.bytes.last() != Some(&b'\n'));
+        if output[start..].starts_with(b"%PDF-") {
+            let end = output[start..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map_or(output.len(), |position| start + position);
+            output[start + 1..end].fill(b' ');
+        }
+        Ok(output)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_bytes()?)
+            .map_err(|e| format!("Failed to write PDF {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Object, Stream};
+
+    /// Header of a PDF/A file: the version line followed by a comment of binary characters.
+    const HEADER: &[u8] = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n";
+
+    fn sample_pdf() -> Vec<u8> {
+        // lopdf writes the version as is, so it carries a placeholder for the binary comment.
+        let mut document = Document::with_version("1.7\n%....");
+        let pages_id = document.new_object_id();
+        let content_id = document.add_object(Stream::new(dictionary! {}, b"q Q".to_vec()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        let mut pdf = Vec::new();
+        document.save_to(&mut pdf).unwrap();
+        pdf[..HEADER.len()].copy_from_slice(HEADER);
+        pdf
+    }
+
+    #[test]
+    fn keeps_the_original_bytes_and_header() {
+        let original = sample_pdf();
+        let mut update = PdfUpdate::from_bytes(original.clone()).unwrap();
+        let info_id = update.document.add_object(dictionary! {
+            "Title" => Object::string_literal("Contrato social"),
+        });
+        update.document.trailer.set("Info", info_id);
+
+        let output = update.to_bytes().unwrap();
+        assert!(output.starts_with(HEADER));
+        assert!(output.starts_with(&original));
+        assert_eq!(
+            output
+                .windows(5)
+                .filter(|window| window == b"%PDF-")
+                .count(),
+            1
+        );
+
+        let document = Document::load_mem(&output).unwrap();
+        let info = document
+            .trailer
+            .get(b"Info")
+            .and_then(Object::as_reference)
+            .and_then(|id| document.get_dictionary(id))
+            .unwrap();
+        assert_eq!(
+            info.get(b"Title").unwrap(),
+            &Object::string_literal("Contrato social")
+        );
+        assert_eq!(document.get_pages().len(), 1);
+    }
+
+    #[test]
+    fn appends_only_changed_objects() {
+        let original = sample_pdf();
+        let mut update = PdfUpdate::from_bytes(original.clone()).unwrap();
+        update
+            .document
+            .catalog_mut()
+            .unwrap()
+            .set("Lang", Object::string_literal("pt-BR"));
+
+        let output = update.to_bytes().unwrap();
+        let appended = String::from_utf8_lossy(&output[original.len()..]).to_string();
+        let objects: Vec<&str> = appended
+            .split("endobj")
+            .filter(|object| object.contains(" obj") && !object.contains("/Type/XRef"))
+            .collect();
+        assert_eq!(objects.len(), 1);
+        assert!(appended.contains("/Lang"));
+        assert!(appended.contains("/Prev"));
+    }
+}
@@ -1,16 +1,32 @@
 use std::path::Path;
 
+use crate::collision::{back_up, resolve_destination};
+use crate::duplicates::record_archived;
 use crate::llm::models::DocumentInfo;
+use crate::llm::{save_json_file, set_file_name};
+use crate::ocr::select_profile;
+use crate::pdf::embed_document_info;
+use crate::pdf::pages::{extract_pages, page_ranges};
+use crate::settings::load_settings;
+use crate::staging::{install, verify_step, StagingDir};
+use crate::toolchain::Tool;
+use crate::utility::{run_utility, PipelineError};
+use crate::verification::verify_document;
+use crate::workspace::load_workspace;
 use regex::Regex;
 use tauri::async_runtime;
-use tauri_plugin_shell::{process::CommandEvent, ShellExt};
 
 #[tauri::command]
 pub async fn final_pipeline(
     handle: tauri::AppHandle,
-    document_info: DocumentInfo,
-) -> Result<(), String> {
-    let parent_dir = Path::new(&document_info.json_file_path).parent().expect("Failed to get parent directory");
+    mut document_info: DocumentInfo,
+    ocr_profile: Option<String>,
+) -> Result<DocumentInfo, PipelineError> {
+    let settings = load_settings(&handle)?;
+    let ocr_profile = select_profile(&settings, &document_info, ocr_profile.as_deref())?;
+    let output_profile = ocr_profile.output_profile(&settings);
+    let json_file_path = document_info.json_file_path.clone();
+    let parent_dir = Path::new(&json_file_path).parent().expect("Failed to get parent directory");
     let re = Regex::new(r"(.+)-data$").unwrap();
     let original_file = re.replace(&parent_dir.to_string_lossy(), "$1").to_string();
     let original_file = Path::new(&original_file).with_extension("pdf");
@@ -20,111 +36,137 @@ pub async fn final_pipeline(
         std::fs::create_dir_all(&done_dir).map_err(|_| "Failed to create done directory")?;
     }
 
-    let save_path = done_dir.join(document_info.file_name).with_extension("pdf");
-
-    if save_path.exists() {
-        std::fs::remove_file(&save_path).map_err(|_| "Failed to delete file")?;
+    let destination = resolve_destination(
+        &handle,
+        &done_dir,
+        &document_info.file_name,
+        &json_file_path,
+        settings.collision_policy,
+    )?;
+    if destination.file_name != document_info.file_name {
+        println!(
+            "{} is taken by another document, finishing as {}",
+            document_info.file_name, destination.file_name
+        );
     }
-
-    let mut pages = vec![];
-
-    for page in document_info.pages_paths {
-        let page_number = extract_page_number(&page);
-        pages.push(page_number.to_string());
+    let save_path = destination.path.clone();
+    let staging = StagingDir::new(parent_dir)?;
+
+    let workspace = load_workspace(parent_dir)?;
+    let (excluded_pages, content_pages): (Vec<u32>, Vec<u32>) = document_info
+        .source_pages()?
+        .into_iter()
+        .partition(|page| workspace.is_skipped(*page));
+    if content_pages.is_empty() {
+        return Err("Every page of the document is marked as blank or separator".into());
     }
-
-    let success = call_utility(
-        handle.clone(),
-        "qpdf".to_owned(),
-        vec![
-            "--empty".to_string(),
-            "--pages".to_string(),
-            original_file.to_string_lossy().to_string(),
-            pages.join(","),
-            "--".to_string(),
-            save_path.to_string_lossy().to_string(),
-        ],
+    if !excluded_pages.is_empty() {
+        println!("Leaving out blank and separator pages {:?}", excluded_pages);
+    }
+    document_info.excluded_pages = excluded_pages;
+    // Kept pages that look blank are not expected to get any text from OCR.
+    let blank_pages: Vec<u32> = (1..)
+        .zip(&content_pages)
+        .filter(|(_, page)| workspace.looks_blank(**page))
+        .map(|(position, _)| position)
+        .collect();
+    let pages: Vec<(u32, u32)> = content_pages
+        .into_iter()
+        .map(|page| (page, workspace.rotation(page)))
+        .collect();
+    let ranges = page_ranges(&pages);
+    println!(
+        "Extracting pages {} of {}",
+        ranges
+            .iter()
+            .map(|range| range.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        original_file.display()
+    );
+
+    let page_count = pages.len();
+    let extracted_path = staging.file("pages.pdf");
+    let extract_path = extracted_path.clone();
+    async_runtime::spawn_blocking(move || extract_pages(&original_file, &ranges, &extract_path))
+        .await
+        .map_err(|e| format!("Failed to extract pages: {}", e))??;
+    verify_step("Page extraction", &extracted_path, page_count)?;
+
+    println!("Running OCR with profile {}", ocr_profile.name);
+    let ocr_path = staging.file("ocr.pdf");
+    let mut ocrmypdf_args = ocr_profile.ocrmypdf_args(&document_info, output_profile);
+    ocrmypdf_args.extend([
+        extracted_path.to_string_lossy().to_string(),
+        ocr_path.to_string_lossy().to_string(),
+    ]);
+    run_utility(
+        &handle,
+        Tool::Ocrmypdf,
+        ocrmypdf_args,
+        &document_info.json_file_path,
+    )
+    .await?;
+    verify_step("OCR", &ocr_path, page_count)?;
+
+    // The JSON keeps the name of the file in `done/` until the new one is installed, so the new
+    // name is only lent to the embedded metadata.
+    let previous_name =
+        std::mem::replace(&mut document_info.file_name, destination.file_name.clone());
+    let embedded = embed_document_info(&ocr_path, &document_info, output_profile);
+    document_info.file_name = previous_name;
+    embedded?;
+    let report = verify_document(
+        &handle,
+        &ocr_path,
+        page_count,
+        &blank_pages,
+        output_profile,
+        &settings,
     )
     .await;
-
-    if success {
-        let success = call_utility(
-            handle.clone(),
-            "ocrmypdf.exe".to_owned(),
-            vec![
-                "--force-ocr".to_string(),
-                "--pdf-renderer".to_string(),
-                "hocr".to_string(),
-                "--color-conversion-strategy".to_string(),
-                "UseDeviceIndependentColor".to_string(),
-                "-l".to_string(),
-                "por".to_string(),
-                "--clean".to_string(),
-                "--output-type".to_string(),
-                "pdfa-2".to_string(),
-                save_path.to_string_lossy().to_string(),
-                save_path.to_string_lossy().to_string(),
-            ],
-        ).await;
-        if !success {
-            return Err("Failed to call utility".to_string());
-        }
+    let passed = report.passed;
+    document_info.verification = Some(report.clone());
+    let serialized_json = serde_json::to_string(&document_info)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    save_json_file(&serialized_json, Path::new(&json_file_path))?;
+    if !passed {
+        return Err(PipelineError::Verification { report });
     }
 
-    Ok(())
-}
-
-async fn call_utility(handle: tauri::AppHandle, utility: String, args: Vec<String>) -> bool {
-    let spawn_utility = async_runtime::spawn(async move {
-        let (mut rx, child) = handle
-            .shell()
-            .command(utility)
-            .args(args)
-            .spawn()
-            .expect("Failed to spawn process");
-
-        let mut is_success = false;
-
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(data) => {
-                    println!("{}", String::from_utf8_lossy(&data));
-                }
-                CommandEvent::Stderr(data) => {
-                    println!("{}", String::from_utf8_lossy(&data));
-                }
-                CommandEvent::Terminated(status) => {
-                    if let Some(code) = status.code {
-                        println!("Process terminated with status: {}", code);
-                        if code == 0 {
-                            is_success = true;
-                        }
-                    }
-                    if let Some(signal) = status.signal {
-                        println!("Process terminated with signal: {}", signal);
-                    }
-                }
-                _ => {}
-            }
-        }
-        child.kill().expect("Failed to kill process");
-        is_success
-    });
-
-    spawn_utility.await.unwrap()
-}
+    if let Some(replaced) = &destination.replaces {
+        back_up(replaced)?;
+    }
+    install(&ocr_path, &save_path)?;
+    if set_file_name(&mut document_info, destination.file_name) {
+        let serialized_json = serde_json::to_string(&document_info)
+            .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+        save_json_file(&serialized_json, Path::new(&json_file_path))?;
+    }
+    if let Err(e) = record_archived(&handle, &document_info, &save_path).await {
+        println!("Failed to record archived document: {}", e);
+    }
 
-fn extract_page_number(input: &str) -> &str {
-    let re = Regex::new(r"page-(\d+)").expect("Regex should never fail");
-    re.captures(input)
-        .and_then(|caps| caps.get(1).map(|m| m.as_str()))
-        .unwrap_or("unidentified")
+    Ok(document_info)
 }
 
+/// Shows a file in the platform file manager, selecting it where the file manager supports it.
 #[tauri::command]
 pub fn open_in_explorer(path: &str) -> Result<(), String> {
-    let mut command = std::process::Command::new("explorer");
-    command.args(&["/select,", path]);
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("explorer");
+        command.args(["/select,", path]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("open");
+        command.args(["-R", path]);
+        command
+    } else {
+        let dir = Path::new(path).parent().unwrap_or(Path::new(path));
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(dir);
+        command
+    };
     command.spawn().map_err(|_| "Failed to open in explorer")?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,205 @@
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri::{async_runtime, Emitter};
+
+use crate::pdf::load_pdf;
+use crate::pdf::pages::page_long_sides;
+use crate::settings::{load_settings, Settings};
+use crate::toolchain::{tool_path, Tool};
+use crate::utility::run_utility;
+
+/// Renderers in order of preference: poppler is faster and ships with most Linux desktops.
+pub const RENDERERS: [Tool; 2] = [Tool::Pdftoppm, Tool::Magick];
+const PROGRESS_EVENT: &str = "rasterize-progress";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RasterizeProgress {
+    pub pdf_path: String,
+    pub page: u32,
+    pub total: u32,
+    /// Whether the page image already existed and was kept.
+    pub skipped: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RasterizeReport {
+    pub renderer: Option<Tool>,
+    pub total: u32,
+    pub rendered: u32,
+    pub skipped: u32,
+}
+
+pub fn page_image_path(data_dir: &Path, page: u32) -> PathBuf {
+    data_dir.join(format!("page-{}.jpg", page))
+}
+
+/// Number of the page of a `page-N.jpg` image, leaving out the other images of the directory.
+pub fn page_image_number(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_prefix("page-")?.strip_suffix(".jpg")?.parse().ok()
+}
+
+/// Number of pages and the longest side of each page, in points.
+fn page_sizes(pdf_path: &Path) -> Result<(u32, BTreeMap<u32, f32>), String> {
+    let document = load_pdf(pdf_path)?;
+    Ok((document.get_pages().len() as u32, page_long_sides(&document)))
+}
+
+/// First renderer that is available.
+fn renderer(handle: &tauri::AppHandle) -> Result<Tool, String> {
+    RENDERERS
+        .iter()
+        .copied()
+        .find(|tool| tool_path(handle, *tool).is_ok())
+        .ok_or_else(|| {
+            "No PDF renderer was found. Install poppler (pdftoppm) or ImageMagick, or set its path in the settings".to_string()
+        })
+}
+
+/// Renders one page straight to a lossy JPEG at the resolution of the settings, scaled down to
+/// fit within their maximum size. `long_side` is the longest side of the page in points, used to
+/// tell pdftoppm when to scale; ImageMagick only shrinks on its own.
+async fn render_page(
+    handle: &tauri::AppHandle,
+    tool: Tool,
+    pdf_path: &Path,
+    page: u32,
+    long_side: Option<f32>,
+    settings: &Settings,
+    jpeg_path: &Path,
+) -> Result<(), String> {
+    let pdf = pdf_path.to_string_lossy().to_string();
+    let (dpi, max_size) = (settings.render_dpi, settings.render_max_size);
+    let quality = settings.render_quality.clamp(1, 100);
+    let args = match tool {
+        Tool::Pdftoppm => {
+            let mut args = vec![
+                "-f".to_string(),
+                page.to_string(),
+                "-l".to_string(),
+                page.to_string(),
+                "-r".to_string(),
+                dpi.to_string(),
+            ];
+            if long_side.map_or(true, |points| points / 72.0 * dpi as f32 > max_size as f32) {
+                args.extend(["-scale-to".to_string(), max_size.to_string()]);
+            }
+            // pdftoppm adds the extension to the output prefix itself.
+            let prefix = jpeg_path.with_extension("").to_string_lossy().to_string();
+            args.extend([
+                "-jpeg".to_string(),
+                "-jpegopt".to_string(),
+                format!("quality={}", quality),
+                "-singlefile".to_string(),
+                pdf,
+                prefix,
+            ]);
+            args
+        }
+        _ => vec![
+            "-density".to_string(),
+            dpi.to_string(),
+            format!("{}[{}]", pdf, page - 1),
+            "-background".to_string(),
+            "white".to_string(),
+            "-alpha".to_string(),
+            "remove".to_string(),
+            "-resize".to_string(),
+            format!("{}x{}>", max_size, max_size),
+            "-quality".to_string(),
+            quality.to_string(),
+            "-strip".to_string(),
+            format!("jpg:{}", jpeg_path.to_string_lossy()),
+        ],
+    };
+
+    run_utility(handle, tool, args, &pdf_path.to_string_lossy())
+        .await
+        .map_err(|e| format!("Failed to render page {}: {}", page, e))?;
+    if !jpeg_path.exists() {
+        return Err(format!(
+            "Failed to render page {}: no image was written",
+            page
+        ));
+    }
+    Ok(())
+}
+
+/// Renders the pages of a PDF as `page-N.jpg` images in its `-data` directory, at the DPI and
+/// size of the settings. Pages that are already rendered are kept, so an interrupted extraction
+/// resumes where it stopped. Emits a `rasterize-progress` event per page.
+#[tauri::command]
+pub async fn rasterize_pdf(
+    handle: tauri::AppHandle,
+    pdf_path: String,
+    data_path: String,
+) -> Result<RasterizeReport, String> {
+    let settings = load_settings(&handle)?;
+    let pdf = PathBuf::from(&pdf_path);
+    let data_dir = PathBuf::from(&data_path);
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let sizes_path = pdf.clone();
+    let (total, long_sides) = async_runtime::spawn_blocking(move || page_sizes(&sizes_path))
+        .await
+        .map_err(|e| format!("Failed to read PDF: {}", e))??;
+
+    let mut report = RasterizeReport {
+        renderer: None,
+        total,
+        rendered: 0,
+        skipped: 0,
+    };
+    let mut selected_renderer = None;
+
+    for page in 1..=total {
+        let image_path = page_image_path(&data_dir, page);
+        let skipped = image_path.exists();
+        if skipped {
+            report.skipped += 1;
+        } else {
+            if selected_renderer.is_none() {
+                selected_renderer = Some(renderer(&handle)?);
+            }
+            let renderer = selected_renderer.expect("Renderer was just selected");
+            report.renderer = Some(renderer);
+
+            // Rendered under a temporary name first, so that an interrupted render is not taken
+            // for a finished one.
+            let render_path = data_dir.join(format!("page-{}.render.jpg", page));
+            render_page(
+                &handle,
+                renderer,
+                &pdf,
+                page,
+                long_sides.get(&page).copied(),
+                &settings,
+                &render_path,
+            )
+            .await?;
+            fs::rename(&render_path, &image_path)
+                .map_err(|e| format!("Failed to write {}: {}", image_path.display(), e))?;
+            report.rendered += 1;
+        }
+
+        let progress = RasterizeProgress {
+            pdf_path: pdf_path.clone(),
+            page,
+            total,
+            skipped,
+        };
+        if let Err(e) = handle.emit(PROGRESS_EVENT, progress) {
+            println!("Failed to emit progress: {}", e);
+        }
+    }
+
+    println!(
+        "Rasterized {}: {} pages rendered, {} already rendered",
+        pdf_path, report.rendered, report.skipped
+    );
+    Ok(report)
+}
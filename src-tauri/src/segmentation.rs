@@ -0,0 +1,239 @@
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use tauri_plugin_http::reqwest;
+
+use crate::entities::find_identifiers;
+use crate::llm::{anthropic_api_key, transcribe_page};
+use crate::taxonomy::{load_taxonomy, Taxonomy};
+use crate::text::normalize;
+use crate::transcription::{text_lines, TextLine};
+use crate::workspace::{load_workspace, Workspace};
+
+/// Lines at the top of a page that are looked at for a document heading.
+const HEADING_LINES: usize = 6;
+
+/// What can be told about a single page from its transcription alone.
+#[derive(Debug, Clone)]
+struct PageProfile {
+    path: String,
+    page: u32,
+    /// "Página k de n" marker, when the page carries one.
+    position: Option<(u32, u32)>,
+    /// Document type recognized from a heading at the top of the page.
+    heading_type: Option<String>,
+    identifiers: Vec<String>,
+    attachment: bool,
+    empty: bool,
+}
+
+impl PageProfile {
+    fn new(path: &str, page: u32, lines: &[TextLine], taxonomy: &Taxonomy) -> Self {
+        let heading = &lines[..lines.len().min(HEADING_LINES)];
+        let text = lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Self {
+            path: path.to_string(),
+            page,
+            position: lines.iter().find_map(|line| page_position(&line.text)),
+            heading_type: heading
+                .iter()
+                .find_map(|line| taxonomy.match_heading(&line.text))
+                .map(|entry| entry.id.clone()),
+            identifiers: find_identifiers(&text),
+            attachment: heading
+                .iter()
+                .any(|line| normalize(&line.text).starts_with("ANEXO")),
+            empty: lines.is_empty(),
+        }
+    }
+}
+
+fn page_position(text: &str) -> Option<(u32, u32)> {
+    let re = Regex::new(r"\b(?:PAGINA|PAG|FOLHA|FLS?)\s+(\d{1,3})\s+(?:DE\s+)?(\d{1,3})\b")
+        .expect("Regex should never fail");
+    let normalized = normalize(text);
+    let captures = re.captures(&normalized)?;
+    let (index, total) = (captures[1].parse().ok()?, captures[2].parse().ok()?);
+    (index >= 1 && index <= total).then_some((index, total))
+}
+
+/// Whether a page starts a new document, how sure we are and why.
+struct Decision {
+    boundary: bool,
+    confidence: f64,
+    reason: String,
+}
+
+impl Decision {
+    fn boundary(confidence: f64, reason: String) -> Self {
+        Self {
+            boundary: true,
+            confidence,
+            reason,
+        }
+    }
+
+    fn continuation(confidence: f64, reason: String) -> Self {
+        Self {
+            boundary: false,
+            confidence,
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PageGroup {
+    pub pages_paths: Vec<String>,
+    /// Page numbers of `pages_paths`, in the same order.
+    pub pages: Vec<u32>,
+    /// Pages of the group that were recognized as attachments of the main document.
+    pub attachments: Vec<String>,
+    /// Taxonomy id of the document type recognized in the headings of the group.
+    pub document_type: Option<String>,
+    /// Confidence between 0.0 and 1.0 of the weakest decision that shaped the group.
+    pub confidence: f64,
+    pub reasons: Vec<String>,
+}
+
+impl PageGroup {
+    fn start(page: &PageProfile, decision: Decision) -> Self {
+        Self {
+            pages_paths: vec![page.path.clone()],
+            pages: vec![page.page],
+            attachments: Vec::new(),
+            document_type: page.heading_type.clone(),
+            confidence: decision.confidence,
+            reasons: vec![decision.reason],
+        }
+    }
+}
+
+fn decide(
+    previous: &PageProfile,
+    group: &PageGroup,
+    identifiers: &[String],
+    page: &PageProfile,
+) -> Decision {
+    if let (Some((index, total)), Some((previous_index, previous_total))) =
+        (page.position, previous.position)
+    {
+        if index > 1 && total == previous_total && index == previous_index + 1 {
+            return Decision::continuation(
+                0.95,
+                format!(
+                    "Page {} of {} follows page {}",
+                    index, total, previous_index
+                ),
+            );
+        }
+    }
+    if let Some((1, total)) = page.position {
+        return Decision::boundary(0.9, format!("Page 1 of {} starts a new document", total));
+    }
+    if let Some((index, total)) = previous.position {
+        if index < total {
+            return Decision::continuation(
+                0.85,
+                format!("Previous page is page {} of {}", index, total),
+            );
+        }
+    }
+    if page.attachment {
+        return Decision::continuation(0.7, "Page is headed as an attachment".to_string());
+    }
+    if let Some(heading_type) = &page.heading_type {
+        return match &group.document_type {
+            Some(group_type) if group_type == heading_type => {
+                Decision::boundary(0.6, format!("Page repeats the {} heading", heading_type))
+            }
+            _ => Decision::boundary(0.85, format!("Page is headed as {}", heading_type)),
+        };
+    }
+    if !page.identifiers.is_empty()
+        && !identifiers.is_empty()
+        && !page.identifiers.iter().any(|id| identifiers.contains(id))
+    {
+        return Decision::boundary(
+            0.7,
+            "Page mentions none of the CNPJs/CPFs of the previous pages".to_string(),
+        );
+    }
+    if page.empty {
+        return Decision::continuation(0.5, "Page has no text".to_string());
+    }
+    Decision::continuation(0.6, "Nothing suggests a new document".to_string())
+}
+
+/// Groups consecutive pages into documents, marking the pages that follow an "Anexo" heading as
+/// attachments of the group.
+fn segment(pages: &[PageProfile]) -> Vec<PageGroup> {
+    let mut groups: Vec<PageGroup> = Vec::new();
+    let mut identifiers: Vec<String> = Vec::new();
+    let mut in_attachment = false;
+
+    for (index, page) in pages.iter().enumerate() {
+        let decision = match (index.checked_sub(1), groups.last()) {
+            (Some(previous), Some(group)) => decide(&pages[previous], group, &identifiers, page),
+            _ => Decision::boundary(1.0, "First page".to_string()),
+        };
+
+        if decision.boundary {
+            groups.push(PageGroup::start(page, decision));
+            identifiers.clear();
+            in_attachment = false;
+        } else if let Some(group) = groups.last_mut() {
+            group.pages_paths.push(page.path.clone());
+            group.pages.push(page.page);
+            in_attachment |= page.attachment;
+            if in_attachment {
+                group.attachments.push(page.path.clone());
+            }
+            if group.document_type.is_none() {
+                group.document_type = page.heading_type.clone();
+            }
+            group.confidence = group.confidence.min(decision.confidence);
+            group.reasons.push(decision.reason);
+        }
+
+        for id in &page.identifiers {
+            if !identifiers.contains(id) {
+                identifiers.push(id.clone());
+            }
+        }
+    }
+
+    groups
+}
+
+/// Proposes how the pages of a multi-document scan split into documents, from the transcription
+/// of each page. Transcriptions are cached per page, so `anthropic_pipeline` reuses them. Blank
+/// and separator pages are left out.
+#[tauri::command]
+pub async fn propose_segmentation(
+    handle: tauri::AppHandle,
+    paths: Vec<String>,
+    pages: Vec<u32>,
+) -> Result<Vec<PageGroup>, String> {
+    let taxonomy = load_taxonomy(&handle)?;
+    let client = reqwest::Client::new();
+    let api_key = anthropic_api_key()?;
+
+    let workspace = match paths.first().and_then(|path| Path::new(path).parent()) {
+        Some(data_dir) => load_workspace(data_dir)?,
+        None => Workspace::default(),
+    };
+
+    let mut profiles = Vec::new();
+    for (path, page) in workspace.content_pages(&paths, &pages)? {
+        let xml = transcribe_page(&client, &api_key, &path, page).await?;
+        profiles.push(PageProfile::new(&path, page, &text_lines(&xml)?, &taxonomy));
+    }
+
+    Ok(segment(&profiles))
+}
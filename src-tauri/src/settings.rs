@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use tauri::{async_runtime, Manager};
+
+use crate::collision::CollisionPolicy;
+use crate::naming;
+use crate::ocr::{self, OcrProfile};
+use crate::toolchain::{refresh_toolchain, Tool};
+use crate::utility;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+/// Largest value accepted for `max_file_size_mb`, 1 TB.
+const MAX_FILE_SIZE_LIMIT_MB: u64 = 1024 * 1024;
+
+/// Archival profile of the finished PDFs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputProfile {
+    /// PDF/A-2b, as produced by ocrmypdf.
+    #[default]
+    #[serde(rename = "pdfa-2")]
+    Pdfa2,
+    /// PDF/A-3b with the transcription and the extracted data embedded as associated files.
+    #[serde(rename = "pdfa-3")]
+    Pdfa3,
+}
+
+impl OutputProfile {
+    /// Value of the ocrmypdf `--output-type` option.
+    pub fn output_type(&self) -> &'static str {
+        match self {
+            OutputProfile::Pdfa2 => "pdfa-2",
+            OutputProfile::Pdfa3 => "pdfa-3",
+        }
+    }
+
+    /// Value of the veraPDF `--flavour` option for the files ocrmypdf writes.
+    pub fn pdfa_flavour(&self) -> &'static str {
+        match self {
+            OutputProfile::Pdfa2 => "2b",
+            OutputProfile::Pdfa3 => "3b",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub naming_template: String,
+    /// Paths of external tools that are neither bundled nor on the PATH.
+    pub tool_paths: BTreeMap<Tool, String>,
+    pub output_profile: OutputProfile,
+    /// Resolution at which PDF pages are rendered into page images.
+    pub render_dpi: u32,
+    /// Largest width or height of a page image, in pixels.
+    pub render_max_size: u32,
+    /// JPEG quality of the page images, from 1 to 100.
+    pub render_quality: u8,
+    pub ocr_profiles: Vec<OcrProfile>,
+    /// Profile used for documents whose type has no profile of its own.
+    pub default_ocr_profile: String,
+    /// Wall-clock limits of the external tools, in seconds, overriding their defaults.
+    pub tool_timeouts: BTreeMap<Tool, u64>,
+    /// How many OCR jobs may run at the same time.
+    pub max_ocr_jobs: usize,
+    /// What happens when a document would take the file name of another finished document.
+    pub collision_policy: CollisionPolicy,
+    /// Largest size of a finished document, in megabytes.
+    pub max_file_size_mb: u64,
+    /// Whether finished documents are validated with veraPDF when it is installed.
+    pub validate_pdfa: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            naming_template: naming::DEFAULT_TEMPLATE.to_string(),
+            tool_paths: BTreeMap::new(),
+            output_profile: OutputProfile::default(),
+            render_dpi: 150,
+            render_max_size: 1500,
+            render_quality: 80,
+            ocr_profiles: vec![OcrProfile::default()],
+            default_ocr_profile: ocr::DEFAULT_PROFILE.to_string(),
+            tool_timeouts: BTreeMap::new(),
+            max_ocr_jobs: utility::default_ocr_jobs(),
+            collision_policy: CollisionPolicy::default(),
+            max_file_size_mb: 50,
+            validate_pdfa: true,
+        }
+    }
+}
+
+fn config_path(handle: &tauri::AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    let config_dir = handle
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    Ok(config_dir.join(file_name))
+}
+
+/// Reads a configuration file from the config directory, falling back to the bundled default.
+pub fn read_config_file(
+    handle: &tauri::AppHandle,
+    file_name: &str,
+    default: &str,
+) -> Result<String, String> {
+    let path = config_path(handle, file_name)?;
+    if !path.exists() {
+        return Ok(default.to_string());
+    }
+    fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", file_name, e))
+}
+
+pub fn load_settings(handle: &tauri::AppHandle) -> Result<Settings, String> {
+    let path = config_path(handle, SETTINGS_FILE_NAME)?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse settings: {}", e))
+}
+
+fn save_settings(handle: &tauri::AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = config_path(handle, SETTINGS_FILE_NAME)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_settings(handle: tauri::AppHandle) -> Result<Settings, String> {
+    load_settings(&handle)
+}
+
+/// Validates and saves the settings, then detects the tools again in case their paths changed.
+#[tauri::command]
+pub async fn update_settings(
+    handle: tauri::AppHandle,
+    settings: Settings,
+) -> Result<Settings, String> {
+    naming::parse_template(&settings.naming_template)?;
+    if settings.render_dpi == 0 || settings.render_max_size == 0 {
+        return Err("Render resolution and size must be greater than zero".to_string());
+    }
+    if !(1..=100).contains(&settings.render_quality) {
+        return Err("Render quality must be between 1 and 100".to_string());
+    }
+    ocr::validate_profiles(&settings)?;
+    if settings.max_ocr_jobs == 0 || settings.tool_timeouts.values().any(|secs| *secs == 0) {
+        return Err("OCR jobs and tool timeouts must be greater than zero".to_string());
+    }
+    if settings.max_file_size_mb == 0 || settings.max_file_size_mb > MAX_FILE_SIZE_LIMIT_MB {
+        return Err(format!(
+            "Maximum file size must be between 1 and {} MB",
+            MAX_FILE_SIZE_LIMIT_MB
+        ));
+    }
+    save_settings(&handle, &settings)?;
+    async_runtime::spawn_blocking(move || refresh_toolchain(&handle))
+        .await
+        .map_err(|e| format!("Failed to detect tools: {}", e))?;
+    Ok(settings)
+}
@@ -0,0 +1,107 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+use crate::pdf::load_pdf;
+
+/// Directory next to `done/` where finished documents are built, so that they are moved into
+/// place with a rename on the same file system.
+const STAGING_DIR_NAME: &str = ".staging";
+
+/// Private working directory of one `final_pipeline` run, removed when dropped whether the run
+/// succeeded or not.
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    pub fn new(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir
+            .join(STAGING_DIR_NAME)
+            .join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+        Ok(Self { path })
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            println!(
+                "Failed to remove staging directory {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Checks that a step produced a readable PDF with the expected number of pages.
+pub fn verify_step(step: &str, path: &Path, expected_pages: usize) -> Result<(), String> {
+    let size = fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("{} produced no file: {}", step, e))?;
+    if size == 0 {
+        return Err(format!("{} produced an empty file", step));
+    }
+
+    let pages = load_pdf(path)
+        .map_err(|e| format!("{} produced an unreadable PDF: {}", step, e))?
+        .get_pages()
+        .len();
+    if pages != expected_pages {
+        return Err(format!(
+            "{} produced {} pages instead of {}",
+            step, pages, expected_pages
+        ));
+    }
+    Ok(())
+}
+
+fn backup_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    destination.with_file_name(name)
+}
+
+/// Moves a staged file to its destination. A file already there is renamed to a backup first and
+/// only removed once the new one is in place; if the move fails, the backup is restored.
+pub fn install(staged: &Path, destination: &Path) -> Result<(), String> {
+    let backup = destination.exists().then(|| backup_path(destination));
+    if let Some(backup) = &backup {
+        fs::rename(destination, backup)
+            .map_err(|e| format!("Failed to back up {}: {}", destination.display(), e))?;
+    }
+
+    if let Err(e) = fs::rename(staged, destination) {
+        if let Some(backup) = &backup {
+            if let Err(restore_error) = fs::rename(backup, destination) {
+                println!(
+                    "Failed to restore {} from {}: {}",
+                    destination.display(),
+                    backup.display(),
+                    restore_error
+                );
+            }
+        }
+        return Err(format!(
+            "Failed to move the finished document to {}: {}",
+            destination.display(),
+            e
+        ));
+    }
+
+    if let Some(backup) = &backup {
+        if let Err(e) = fs::remove_file(backup) {
+            println!("Failed to remove backup {}: {}", backup.display(), e);
+        }
+    }
+    Ok(())
+}
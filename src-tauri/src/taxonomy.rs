@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::llm::models::CanonicalType;
+use crate::settings::read_config_file;
+use crate::text::{compact, normalize, similarity};
+
+const TAXONOMY_FILE_NAME: &str = "document_types.json";
+const DEFAULT_TAXONOMY: &str = include_str!("../resources/document_types.json");
+const MATCH_THRESHOLD: f64 = 0.8;
+/// Added to the name score of an entry whose abbreviation matches exactly.
+const ABBR_BONUS: f64 = 0.1;
+/// Shorter names (e.g. "RG") are too ambiguous to be recognized in free text.
+const MIN_HEADING_LENGTH: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentTypeEntry {
+    pub id: String,
+    pub name: String,
+    pub abbr: String,
+    /// Days a document of this type stays valid after its issue date, when it does not say so.
+    #[serde(default)]
+    pub validity_days: Option<i64>,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Taxonomy {
+    pub types: Vec<DocumentTypeEntry>,
+}
+
+impl Taxonomy {
+    /// Lists the allowed document types for the prompt, one per line.
+    pub fn prompt_list(&self) -> String {
+        self.types
+            .iter()
+            .map(|entry| format!("    - {} ({})", entry.name, entry.abbr))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DocumentTypeEntry> {
+        self.types.iter().find(|entry| entry.id == id)
+    }
+
+    /// Finds the document type whose name or synonym appears as a heading line, preferring the
+    /// longest match.
+    pub fn match_heading(&self, line: &str) -> Option<&DocumentTypeEntry> {
+        let line = format!(" {} ", normalize(line));
+        self.types
+            .iter()
+            .flat_map(|entry| {
+                std::iter::once(&entry.name)
+                    .chain(entry.synonyms.iter())
+                    .map(move |candidate| (entry, normalize(candidate)))
+            })
+            .filter(|(_, candidate)| candidate.len() >= MIN_HEADING_LENGTH)
+            .filter(|(_, candidate)| line.contains(&format!(" {} ", candidate)))
+            .max_by_key(|(_, candidate)| candidate.len())
+            .map(|(entry, _)| entry)
+    }
+
+    /// Maps the type name and abbreviation produced by the model onto the closest canonical entry.
+    pub fn match_document_type(&self, type_name: &str, type_abbr: &str) -> Option<CanonicalType> {
+        let mut best: Option<(&DocumentTypeEntry, f64)> = None;
+
+        for entry in &self.types {
+            let score = entry_score(entry, type_name, type_abbr);
+            if best.map_or(true, |(_, best_score)| score > best_score) {
+                best = Some((entry, score));
+            }
+        }
+
+        best.filter(|(_, score)| *score >= MATCH_THRESHOLD)
+            .map(|(entry, score)| CanonicalType {
+                id: entry.id.clone(),
+                type_name: entry.name.clone(),
+                type_abbr: entry.abbr.clone(),
+                score,
+            })
+    }
+}
+
+fn entry_score(entry: &DocumentTypeEntry, type_name: &str, type_abbr: &str) -> f64 {
+    let names = std::iter::once(&entry.name).chain(entry.synonyms.iter());
+
+    let name_score = names
+        .clone()
+        .map(|candidate| similarity(candidate, type_name))
+        .fold(0.0, f64::max);
+
+    // Abbreviations are too short for fuzzy matching, so they only count when they match exactly.
+    let abbr = compact(type_abbr);
+    let abbr_matches = !abbr.is_empty()
+        && std::iter::once(&entry.abbr)
+            .chain(names)
+            .any(|candidate| compact(candidate) == abbr);
+
+    // Models reuse abbreviations loosely, so a matching one only tips a close name over the
+    // threshold and cannot make a match on its own.
+    if abbr_matches {
+        (name_score + ABBR_BONUS).min(1.0)
+    } else {
+        name_score
+    }
+}
+
+pub fn load_taxonomy(handle: &tauri::AppHandle) -> Result<Taxonomy, String> {
+    let content = read_config_file(handle, TAXONOMY_FILE_NAME, DEFAULT_TAXONOMY)?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse document types: {}", e))
+}
+
+#[tauri::command]
+pub fn get_document_types(handle: tauri::AppHandle) -> Result<Taxonomy, String> {
+    load_taxonomy(&handle)
+}
@@ -0,0 +1,65 @@
+fn fold_accent(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+        'Á' | 'À' | 'Â' | 'Ã' | 'Ä' => 'A',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'É' | 'È' | 'Ê' | 'Ë' => 'E',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+        'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+        'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' => 'O',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        _ => c,
+    }
+}
+
+/// Uppercases, strips accents and collapses punctuation into single spaces, so that
+/// "Nota Fiscal Eletrônica" and "NOTA FISCAL ELETRONICA" compare equal.
+pub fn normalize(value: &str) -> String {
+    let folded: String = value
+        .chars()
+        .map(fold_accent)
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Normalized form without any separators, so that "NF-E" and "NFE" compare equal.
+pub fn compact(value: &str) -> String {
+    normalize(value).replace(' ', "")
+}
+
+pub fn digits_only(value: &str) -> String {
+    value.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+/// Whether a number found in a text stands on its own rather than being part of a longer run of
+/// digits, such as an NF-e access key or a barcode.
+pub fn stands_alone(text: &str, found: &regex::Match) -> bool {
+    let before = text[..found.start()].chars().next_back();
+    let after = text[found.end()..].chars().next();
+    !before.is_some_and(|c| c.is_ascii_digit()) && !after.is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Similarity between 0.0 and 1.0 of two strings after normalization.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a.replace(' ', "") == b.replace(' ', "") {
+        return 1.0;
+    }
+    strsim::normalized_levenshtein(&a, &b)
+}
@@ -0,0 +1,260 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+use tauri::Manager;
+
+use crate::settings::load_settings;
+
+/// External programs the pipeline spawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tool {
+    Ocrmypdf,
+    Magick,
+    Pdftoppm,
+    Tesseract,
+    /// PDF/A validator, used to check finished documents when installed.
+    Verapdf,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 5] = [
+        Tool::Ocrmypdf,
+        Tool::Magick,
+        Tool::Pdftoppm,
+        Tool::Tesseract,
+        Tool::Verapdf,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Ocrmypdf => "ocrmypdf",
+            Tool::Magick => "magick",
+            Tool::Pdftoppm => "pdftoppm",
+            Tool::Tesseract => "tesseract",
+            Tool::Verapdf => "verapdf",
+        }
+    }
+
+    fn executable(&self) -> String {
+        // veraPDF is started through a batch script on Windows.
+        if *self == Tool::Verapdf && cfg!(target_os = "windows") {
+            return format!("{}.bat", self.name());
+        }
+        format!("{}{}", self.name(), env::consts::EXE_SUFFIX)
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        match self {
+            Tool::Magick => &["-version"],
+            Tool::Pdftoppm => &["-v"],
+            _ => &["--version"],
+        }
+    }
+
+    /// Wall-clock limit of one run, in seconds. ocrmypdf processes a whole document per run, the
+    /// renderers and tesseract a single page.
+    pub fn default_timeout_secs(&self) -> u64 {
+        match self {
+            Tool::Ocrmypdf => 30 * 60,
+            Tool::Magick | Tool::Pdftoppm => 2 * 60,
+            Tool::Tesseract => 60,
+            Tool::Verapdf => 5 * 60,
+        }
+    }
+
+    /// Oldest (major, minor) version known to support the options the pipeline uses.
+    fn min_version(&self) -> (u32, u32) {
+        match self {
+            Tool::Ocrmypdf => (12, 0),
+            Tool::Magick => (7, 0),
+            Tool::Pdftoppm => (0, 86),
+            Tool::Tesseract => (4, 0),
+            Tool::Verapdf => (1, 20),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    Configured,
+    Sidecar,
+    Path,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedTool {
+    pub tool: Tool,
+    pub path: Option<PathBuf>,
+    pub source: Option<ToolSource>,
+    pub version: Option<String>,
+    /// Whether the version is at least the minimum supported one.
+    pub supported: bool,
+}
+
+/// The external tools found at startup, kept as managed state.
+#[derive(Debug, Default)]
+pub struct Toolchain {
+    pub tools: Mutex<Vec<ResolvedTool>>,
+    /// Set once the tools were detected, which happens in the background at startup.
+    detected: AtomicBool,
+}
+
+impl Toolchain {
+    pub fn is_detected(&self) -> bool {
+        self.detected.load(Ordering::Acquire)
+    }
+}
+
+fn find_in_path(executable: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(executable))
+        .find(|path| path.is_file())
+}
+
+/// Finds a tool in its configured path, next to the application as a bundled sidecar, or on the
+/// PATH, in that order.
+fn locate(tool: Tool, configured: Option<&String>) -> Option<(PathBuf, ToolSource)> {
+    if let Some(path) = configured.map(PathBuf::from) {
+        if path.is_file() {
+            return Some((path, ToolSource::Configured));
+        }
+        println!(
+            "Configured path for {} not found: {}",
+            tool.name(),
+            path.display()
+        );
+    }
+
+    let sidecar = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(tool.executable())))
+        .filter(|path| path.is_file());
+    if let Some(path) = sidecar {
+        return Some((path, ToolSource::Sidecar));
+    }
+
+    find_in_path(&tool.executable()).map(|path| (path, ToolSource::Path))
+}
+
+fn parse_version(output: &str) -> Option<(String, (u32, u32))> {
+    let re = Regex::new(r"(\d+)\.(\d+)(?:\.(\d+))?").expect("Regex should never fail");
+    let captures = re.captures(output)?;
+    let major = captures[1].parse().ok()?;
+    let minor = captures[2].parse().ok()?;
+    Some((captures[0].to_string(), (major, minor)))
+}
+
+fn tool_version(tool: Tool, path: &Path) -> Option<(String, (u32, u32))> {
+    let output = Command::new(path).args(tool.version_args()).output().ok()?;
+    // pdftoppm and some tesseract builds print their version on stderr.
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_version(&text)
+}
+
+fn resolve(tool: Tool, configured: Option<&String>) -> ResolvedTool {
+    let Some((path, source)) = locate(tool, configured) else {
+        return ResolvedTool {
+            tool,
+            path: None,
+            source: None,
+            version: None,
+            supported: false,
+        };
+    };
+
+    let version = tool_version(tool, &path);
+    ResolvedTool {
+        tool,
+        path: Some(path),
+        source: Some(source),
+        supported: version
+            .as_ref()
+            .is_some_and(|(_, version)| *version >= tool.min_version()),
+        version: version.map(|(version, _)| version),
+    }
+}
+
+/// Locates every tool and checks its version, logging the ones that are missing or too old.
+pub fn detect_tools(handle: &tauri::AppHandle) -> Vec<ResolvedTool> {
+    let tool_paths = load_settings(handle)
+        .map(|settings| settings.tool_paths)
+        .unwrap_or_default();
+
+    Tool::ALL
+        .iter()
+        .map(|tool| {
+            let resolved = resolve(*tool, tool_paths.get(tool));
+            match (&resolved.path, &resolved.version) {
+                (None, _) => println!("{} not found", tool.name()),
+                (Some(path), version) if !resolved.supported => println!(
+                    "{} at {} has an unsupported version: {}",
+                    tool.name(),
+                    path.display(),
+                    version.as_deref().unwrap_or("unknown")
+                ),
+                (Some(path), version) => println!(
+                    "{} {} at {}",
+                    tool.name(),
+                    version.as_deref().unwrap_or(""),
+                    path.display()
+                ),
+            }
+            resolved
+        })
+        .collect()
+}
+
+/// Resolves the tools again, after startup or when their configured paths change.
+pub fn refresh_toolchain(handle: &tauri::AppHandle) {
+    let tools = detect_tools(handle);
+    let toolchain = handle.state::<Toolchain>();
+    *toolchain.tools.lock().expect("Toolchain lock poisoned") = tools;
+    toolchain.detected.store(true, Ordering::Release);
+}
+
+/// Path of a tool to spawn, or an error telling the user how to make it available. Tools older
+/// than the minimum supported version, or whose version could not be read, are refused.
+pub fn tool_path(handle: &tauri::AppHandle, tool: Tool) -> Result<PathBuf, String> {
+    let toolchain = handle.state::<Toolchain>();
+    if !toolchain.is_detected() {
+        return Err(
+            "The external tools are still being detected, try again in a moment".to_string(),
+        );
+    }
+    let tools = toolchain.tools.lock().expect("Toolchain lock poisoned");
+    let resolved = tools.iter().find(|resolved| resolved.tool == tool);
+    let Some(path) = resolved.and_then(|resolved| resolved.path.clone()) else {
+        return Err(format!(
+            "{} was not found. Install it or set its path in the settings",
+            tool.name()
+        ));
+    };
+    if !resolved.is_some_and(|resolved| resolved.supported) {
+        let (major, minor) = tool.min_version();
+        return Err(format!(
+            "{} at {} has an unsupported version: {}. Install version {}.{} or newer",
+            tool.name(),
+            path.display(),
+            resolved
+                .and_then(|resolved| resolved.version.as_deref())
+                .unwrap_or("unknown"),
+            major,
+            minor
+        ));
+    }
+    Ok(path)
+}
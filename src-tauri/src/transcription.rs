@@ -0,0 +1,63 @@
+use quick_xml::{events::Event, Reader};
+use std::path::{Path, PathBuf};
+
+/// A text node of a transcribed page, in document order.
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub page: Option<u32>,
+    pub text: String,
+}
+
+/// Path of the cached transcription that belongs to a document JSON.
+pub fn xml_path_for(json_path: &Path) -> PathBuf {
+    json_path.with_extension("xml")
+}
+
+/// Path of the cached transcription of a single page image (`page-N.jpg` -> `page-N.xml`).
+pub fn page_xml_path(image_path: &Path) -> PathBuf {
+    image_path.with_extension("xml")
+}
+
+/// Flattens a transcription into its text nodes, keeping track of the `<page number="N">` they
+/// belong to.
+pub fn text_lines(xml: &str) -> Result<Vec<TextLine>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text_start = true;
+    reader.config_mut().trim_text_end = true;
+
+    let mut lines = Vec::new();
+    let mut page = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) if element.name().as_ref() == b"page" => {
+                page = element
+                    .try_get_attribute("number")
+                    .ok()
+                    .flatten()
+                    .and_then(|attribute| attribute.unescape_value().ok())
+                    .and_then(|value| value.trim().parse().ok());
+            }
+            Ok(Event::Text(text)) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| format!("Error parsing XML: {}", e))?;
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !text.is_empty() {
+                    lines.push(TextLine { page, text });
+                }
+            }
+            Ok(Event::CData(data)) => {
+                let text = String::from_utf8_lossy(&data).trim().to_string();
+                if !text.is_empty() {
+                    lines.push(TextLine { page, text });
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("Error parsing XML: {}", e)),
+        }
+    }
+
+    Ok(lines)
+}
@@ -0,0 +1,437 @@
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tauri::{async_runtime, Emitter, Manager};
+use tauri_plugin_shell::{process::CommandEvent, ShellExt};
+use tokio::{sync::Notify, time};
+
+use crate::settings::load_settings;
+use crate::toolchain::{tool_path, Tool};
+use crate::verification::VerificationReport;
+
+/// Lines of stderr kept to explain a failure.
+const STDERR_TAIL_LINES: usize = 20;
+const PROGRESS_EVENT: &str = "utility-progress";
+/// How long a tool asked to terminate gets before it is killed.
+const TERMINATION_GRACE: Duration = Duration::from_secs(10);
+
+/// How a run of an external tool ended.
+#[derive(Debug, Clone, Serialize)]
+pub struct UtilityOutput {
+    pub tool: Tool,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    /// Standard output, for tools that report their results there.
+    #[serde(skip)]
+    pub stdout: String,
+    pub stderr_tail: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl UtilityOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Pages an external tool has worked through, for a job such as the OCR of one document.
+#[derive(Debug, Clone, Serialize)]
+pub struct UtilityProgress {
+    pub tool: Tool,
+    pub job: String,
+    pub pages_done: u32,
+    pub total_pages: Option<u32>,
+}
+
+/// Errors of the document pipeline, tagged by `kind` so the UI can tell them apart.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PipelineError {
+    /// The tool could not be started at all.
+    Spawn {
+        tool: Tool,
+        message: String,
+    },
+    /// The tool ran and exited unsuccessfully.
+    Failed {
+        output: UtilityOutput,
+    },
+    /// The tool ran longer than its timeout and was stopped.
+    Timeout {
+        timeout_secs: u64,
+        output: UtilityOutput,
+    },
+    /// The finished document did not pass its verification.
+    Verification {
+        report: VerificationReport,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Spawn { tool, message } => {
+                write!(f, "Failed to start {}: {}", tool.name(), message)
+            }
+            PipelineError::Failed { output } => {
+                match (output.code, output.signal) {
+                    (Some(code), _) => {
+                        write!(f, "{} exited with code {}", output.tool.name(), code)?
+                    }
+                    (None, Some(signal)) => write!(
+                        f,
+                        "{} was terminated by signal {}",
+                        output.tool.name(),
+                        signal
+                    )?,
+                    (None, None) => write!(f, "{} terminated abnormally", output.tool.name())?,
+                }
+                if let Some(line) = output.stderr_tail.last() {
+                    write!(f, ": {}", line)?;
+                }
+                Ok(())
+            }
+            PipelineError::Timeout {
+                timeout_secs,
+                output,
+            } => write!(
+                f,
+                "{} did not finish within {} s and was stopped",
+                output.tool.name(),
+                timeout_secs
+            ),
+            PipelineError::Verification { report } => {
+                write!(f, "Verification failed: {}", report.failures.join("; "))
+            }
+            PipelineError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for PipelineError {
+    fn from(message: String) -> Self {
+        PipelineError::Other { message }
+    }
+}
+
+impl From<&str> for PipelineError {
+    fn from(message: &str) -> Self {
+        PipelineError::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Follows the page progress that ocrmypdf logs: the page count when it starts, then lines
+/// prefixed with the number of the page they are about. Pages run concurrently, so progress is
+/// the number of distinct pages seen.
+struct OcrProgress {
+    total_pattern: Regex,
+    page_pattern: Regex,
+    total: Option<u32>,
+    pages: BTreeSet<u32>,
+}
+
+impl OcrProgress {
+    fn new() -> Self {
+        Self {
+            total_pattern: Regex::new(r"Start processing (\d+) pages?")
+                .expect("Regex should never fail"),
+            page_pattern: Regex::new(r"^\s*(\d+)\s").expect("Regex should never fail"),
+            total: None,
+            pages: BTreeSet::new(),
+        }
+    }
+
+    /// Reads a log line, returning whether the progress changed.
+    fn update(&mut self, line: &str) -> bool {
+        if let Some(captures) = self.total_pattern.captures(line) {
+            self.total = captures[1].parse().ok();
+            return true;
+        }
+        let page = self
+            .page_pattern
+            .captures(line)
+            .and_then(|captures| captures[1].parse::<u32>().ok())
+            .filter(|page| *page > 0 && self.total.map_or(true, |total| *page <= total));
+        page.is_some_and(|page| self.pages.insert(page))
+    }
+}
+
+/// Caps how many OCR jobs run at once, since each one keeps every core busy. Kept as managed
+/// state; the limit is read from the settings on every acquisition, so changes apply at once.
+#[derive(Debug, Default)]
+pub struct OcrLimiter {
+    running: Mutex<usize>,
+    released: Notify,
+}
+
+/// A running OCR job, released when dropped.
+pub struct OcrPermit<'a> {
+    limiter: &'a OcrLimiter,
+}
+
+impl OcrLimiter {
+    pub async fn acquire(&self, limit: usize) -> OcrPermit<'_> {
+        loop {
+            // Created before checking, so that a release in between is not missed.
+            let released = self.released.notified();
+            {
+                let mut running = self.running.lock().expect("OCR limiter lock poisoned");
+                if *running < limit.max(1) {
+                    *running += 1;
+                    return OcrPermit { limiter: self };
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+impl Drop for OcrPermit<'_> {
+    fn drop(&mut self) {
+        let mut running = self
+            .limiter
+            .running
+            .lock()
+            .expect("OCR limiter lock poisoned");
+        *running = running.saturating_sub(1);
+        self.limiter.released.notify_waiters();
+    }
+}
+
+/// Number of OCR jobs to allow at once by default: half the cores, leaving room for the UI.
+pub fn default_ocr_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| (cores.get() / 2).max(1))
+        .unwrap_or(1)
+}
+
+/// Asks a process to exit, so that it can stop its own children and remove its temporary files.
+fn terminate(pid: u32) {
+    let status = if cfg!(target_os = "windows") {
+        std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T"])
+            .status()
+    } else {
+        std::process::Command::new("kill")
+            .args(["-TERM", &pid.to_string()])
+            .status()
+    };
+    if let Err(e) = status {
+        println!("Failed to terminate process {}: {}", pid, e);
+    }
+}
+
+/// A process and, on Unix, the processes it started, found through `pgrep`. Windows finds the
+/// tree itself with `taskkill /T`.
+fn process_tree(pid: u32) -> Vec<u32> {
+    let mut tree = vec![pid];
+    if cfg!(target_os = "windows") {
+        return tree;
+    }
+    let mut index = 0;
+    while let Some(parent) = tree.get(index).copied() {
+        index += 1;
+        let Ok(output) = std::process::Command::new("pgrep")
+            .args(["-P", &parent.to_string()])
+            .output()
+        else {
+            continue;
+        };
+        for child in String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse::<u32>().ok())
+        {
+            if !tree.contains(&child) {
+                tree.push(child);
+            }
+        }
+    }
+    tree
+}
+
+/// Kills a process and every process it started, which killing the process alone would leave
+/// running, such as the tesseract workers of ocrmypdf.
+fn kill_tree(pid: u32) {
+    let status = if cfg!(target_os = "windows") {
+        std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .status()
+    } else {
+        // Found before killing, since the children of a killed process are no longer its own.
+        std::process::Command::new("kill")
+            .arg("-KILL")
+            .args(process_tree(pid).iter().map(|pid| pid.to_string()))
+            .status()
+    };
+    if let Err(e) = status {
+        println!("Failed to kill process {}: {}", pid, e);
+    }
+}
+
+/// Runs an external tool to completion, logging its output and keeping the end of its stderr.
+/// Page progress of ocrmypdf is emitted as `utility-progress` events for the given job.
+///
+/// A tool that outlives its timeout is asked to terminate, and killed with the processes it
+/// started if it is still running after a grace period. OCR jobs wait for a free slot of the
+/// `OcrLimiter` first, and keep it until the process has exited.
+pub async fn run_utility(
+    handle: &tauri::AppHandle,
+    tool: Tool,
+    args: Vec<String>,
+    job: &str,
+) -> Result<UtilityOutput, PipelineError> {
+    let path = tool_path(handle, tool)?;
+    let settings = load_settings(handle)?;
+    let timeout_secs = settings
+        .tool_timeouts
+        .get(&tool)
+        .copied()
+        .unwrap_or_else(|| tool.default_timeout_secs());
+    let handle = handle.clone();
+    let job = job.to_string();
+
+    let run = async_runtime::spawn(async move {
+        let limiter = handle.state::<OcrLimiter>();
+        let permit = if tool == Tool::Ocrmypdf {
+            println!("Waiting for an OCR slot for {}", job);
+            Some(limiter.acquire(settings.max_ocr_jobs).await)
+        } else {
+            None
+        };
+
+        let started = Instant::now();
+        let (mut rx, child) = handle
+            .shell()
+            .command(&path)
+            .args(args)
+            .spawn()
+            .map_err(|e| PipelineError::Spawn {
+                tool,
+                message: e.to_string(),
+            })?;
+        let pid = child.pid();
+
+        let mut ocr_progress = (tool == Tool::Ocrmypdf).then(OcrProgress::new);
+        let mut stderr_tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let mut output = UtilityOutput {
+            tool,
+            code: None,
+            signal: None,
+            stdout: String::new(),
+            stderr_tail: Vec::new(),
+            duration_ms: 0,
+        };
+        let mut deadline = started + Duration::from_secs(timeout_secs);
+        let mut timed_out = false;
+        let mut killed = false;
+
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            let event = match time::timeout(wait, rx.recv()).await {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(_) if !timed_out => {
+                    println!(
+                        "{} did not finish within {} s, terminating it",
+                        tool.name(),
+                        timeout_secs
+                    );
+                    timed_out = true;
+                    terminate(pid);
+                    deadline = Instant::now() + TERMINATION_GRACE;
+                    continue;
+                }
+                Err(_) if !killed => {
+                    println!("{} did not terminate, killing it", tool.name());
+                    killed = true;
+                    kill_tree(pid);
+                    // The events end once the process has exited.
+                    deadline = Instant::now() + TERMINATION_GRACE;
+                    continue;
+                }
+                Err(_) => {
+                    // Giving up here would free the OCR slot while the process still holds
+                    // its resources, so keep waiting for it to exit.
+                    println!("{} is still running after being killed", tool.name());
+                    deadline = Instant::now() + TERMINATION_GRACE;
+                    continue;
+                }
+            };
+
+            match event {
+                CommandEvent::Stdout(data) => {
+                    let line = String::from_utf8_lossy(&data);
+                    println!("{}", line.trim_end());
+                    output.stdout.push_str(&line);
+                }
+                CommandEvent::Stderr(data) => {
+                    let line = String::from_utf8_lossy(&data).trim_end().to_string();
+                    println!("{}", line);
+                    let progress = ocr_progress
+                        .as_mut()
+                        .and_then(|progress| progress.update(&line).then_some(progress));
+                    if let Some(progress) = progress {
+                        let event = UtilityProgress {
+                            tool,
+                            job: job.clone(),
+                            pages_done: progress.pages.len() as u32,
+                            total_pages: progress.total,
+                        };
+                        if let Err(e) = handle.emit(PROGRESS_EVENT, event) {
+                            println!("Failed to emit progress: {}", e);
+                        }
+                    }
+                    if !line.trim().is_empty() {
+                        if stderr_tail.len() == STDERR_TAIL_LINES {
+                            stderr_tail.pop_front();
+                        }
+                        stderr_tail.push_back(line);
+                    }
+                }
+                CommandEvent::Terminated(status) => {
+                    output.code = status.code;
+                    output.signal = status.signal;
+                }
+                CommandEvent::Error(e) => println!("{} error: {}", tool.name(), e),
+                _ => {}
+            }
+        }
+
+        // Released only now, so that the next OCR job does not start beside one still stopping.
+        drop(permit);
+
+        output.stderr_tail = stderr_tail.into();
+        if timed_out {
+            return Err(PipelineError::Timeout {
+                timeout_secs,
+                output,
+            });
+        }
+        output.duration_ms = started.elapsed().as_millis() as u64;
+        println!(
+            "{} finished in {} ms with code {:?}",
+            tool.name(),
+            output.duration_ms,
+            output.code
+        );
+        Ok::<_, PipelineError>(output)
+    });
+
+    let output = run
+        .await
+        .map_err(|e| PipelineError::from(format!("Failed to run {}: {}", tool.name(), e)))??;
+    if !output.success() {
+        return Err(PipelineError::Failed { output });
+    }
+    Ok(output)
+}
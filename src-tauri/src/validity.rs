@@ -0,0 +1,200 @@
+use chrono::{Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::{fs, path::Path};
+
+use crate::llm::models::{
+    parse_partial_date, parse_partial_end_date, DocumentInfo, Validity, ValiditySource,
+};
+use crate::taxonomy::{load_taxonomy, Taxonomy};
+use crate::workspace::find_documents;
+
+const CALENDAR_NAME: &str = "auxiliar-de-cadastros";
+const REMINDER_DAYS: i64 = 7;
+
+/// Works out the validity window of a document: the expiry date stated in the document or,
+/// failing that, the issue date plus the validity rule of its document type.
+pub fn derive_validity(
+    document_info: &DocumentInfo,
+    taxonomy: &Taxonomy,
+) -> Result<Option<Validity>, String> {
+    let reasoning = &document_info.reasoning;
+    let issue_date = parse_partial_date(&reasoning.validity.issue_date)
+        .or_else(|| reasoning.important_date.parse());
+
+    if let Some(expiry_date) = parse_partial_end_date(&reasoning.validity.expiry_date) {
+        return Ok(Some(Validity {
+            issue_date,
+            expiry_date,
+            source: ValiditySource::Extracted,
+        }));
+    }
+
+    let validity_days = document_info
+        .canonical_type
+        .as_ref()
+        .and_then(|canonical| taxonomy.get(&canonical.id))
+        .and_then(|entry| entry.validity_days);
+    let (Some(issue_date), Some(validity_days)) = (issue_date, validity_days) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Validity {
+        issue_date: Some(issue_date),
+        expiry_date: add_days(issue_date, validity_days)?,
+        source: ValiditySource::Rule,
+    }))
+}
+
+fn add_days(date: NaiveDate, days: i64) -> Result<NaiveDate, String> {
+    Duration::try_days(days)
+        .and_then(|duration| date.checked_add_signed(duration))
+        .ok_or_else(|| format!("Date out of range: {} plus {} days", date, days))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpiringDocument {
+    pub json_file_path: String,
+    pub file_name: String,
+    pub type_name: String,
+    pub expiry_date: NaiveDate,
+    /// Negative when the document has already expired.
+    pub days_left: i64,
+    pub source: ValiditySource,
+}
+
+fn expiring_documents(
+    handle: &tauri::AppHandle,
+    dir: &str,
+    days: i64,
+) -> Result<Vec<ExpiringDocument>, String> {
+    let taxonomy = load_taxonomy(handle)?;
+    let today = Local::now().date_naive();
+    let limit = add_days(today, days)?;
+
+    let mut expiring = Vec::new();
+    for document_info in find_documents(Path::new(dir))? {
+        let validity = match document_info.validity.clone() {
+            Some(validity) => validity,
+            None => match derive_validity(&document_info, &taxonomy)? {
+                Some(validity) => validity,
+                None => continue,
+            },
+        };
+        if validity.expiry_date > limit {
+            continue;
+        }
+        let type_name = document_info
+            .canonical_type
+            .as_ref()
+            .map(|canonical| canonical.type_name.clone())
+            .unwrap_or_else(|| document_info.reasoning.document_type.type_name.clone());
+        expiring.push(ExpiringDocument {
+            json_file_path: document_info.json_file_path,
+            file_name: document_info.file_name,
+            type_name,
+            expiry_date: validity.expiry_date,
+            days_left: (validity.expiry_date - today).num_days(),
+            source: validity.source,
+        });
+    }
+
+    expiring.sort_by_key(|document| document.expiry_date);
+    Ok(expiring)
+}
+
+/// Lists the documents under a directory that expire within the given number of days,
+/// including those that have already expired.
+#[tauri::command]
+pub fn list_expiring_documents(
+    handle: tauri::AppHandle,
+    dir: String,
+    days: i64,
+) -> Result<Vec<ExpiringDocument>, String> {
+    expiring_documents(&handle, &dir, days)
+}
+
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines longer than 75 octets, as required by RFC 5545.
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+fn build_calendar(documents: &[ExpiringDocument]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//{}//Vencimentos//PT", CALENDAR_NAME),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{} - vencimentos", CALENDAR_NAME),
+    ];
+
+    for document in documents {
+        let uid = uuid::Uuid::new_v5(
+            &uuid::Uuid::NAMESPACE_URL,
+            document.json_file_path.as_bytes(),
+        );
+        let summary = format!("Vencimento: {}", document.file_name);
+        let description = format!("{}\n{}", document.type_name, document.json_file_path);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@{}", uid, CALENDAR_NAME),
+            format!("DTSTAMP:{}", stamp),
+            format!(
+                "DTSTART;VALUE=DATE:{}",
+                document.expiry_date.format("%Y%m%d")
+            ),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (document.expiry_date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape_ics_text(&summary)),
+            format!("DESCRIPTION:{}", escape_ics_text(&description)),
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape_ics_text(&summary)),
+            format!("TRIGGER:-P{}D", REMINDER_DAYS),
+            "END:VALARM".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_ics_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// Exports the deadlines of the documents expiring within the given number of days as an
+/// iCalendar file.
+#[tauri::command]
+pub fn export_deadlines_ics(
+    handle: tauri::AppHandle,
+    dir: String,
+    days: i64,
+    output_path: String,
+) -> Result<usize, String> {
+    let documents = expiring_documents(&handle, &dir, days)?;
+    fs::write(&output_path, build_calendar(&documents))
+        .map_err(|e| format!("Failed to write calendar: {}", e))?;
+    Ok(documents.len())
+}
@@ -0,0 +1,175 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::pdf::load_pdf;
+use crate::pdf::text::pages_without_text;
+use crate::settings::{OutputProfile, Settings};
+use crate::toolchain::{tool_path, Tool};
+use crate::utility::{run_utility, PipelineError, UtilityOutput};
+
+/// Exit code of veraPDF when it validated the file and found it non-compliant.
+const VERAPDF_INVALID: i32 = 1;
+/// Failed rules kept from the veraPDF output.
+const MAX_PDFA_DETAILS: usize = 20;
+
+/// Result of validating a finished document with veraPDF.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfaValidation {
+    pub flavour: String,
+    pub compliant: bool,
+    /// Rules the file failed, as veraPDF describes them.
+    pub details: Vec<String>,
+}
+
+/// Checks run on a finished document before it is moved into `done/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub verified_at: String,
+    pub passed: bool,
+    pub expected_pages: usize,
+    /// Pages of the file, when it could be parsed.
+    pub pages: Option<usize>,
+    pub file_size: u64,
+    pub max_file_size: u64,
+    /// Pages of the file, numbered from 1, without a text layer and not detected as blank.
+    pub pages_without_text: Vec<u32>,
+    /// veraPDF result, when it is installed and validation is enabled.
+    pub pdfa: Option<PdfaValidation>,
+    pub failures: Vec<String>,
+}
+
+/// Reads the text report of veraPDF: a `PASS` or `FAIL` line for the file, followed by the
+/// failed rules when run with `--verbose`.
+fn parse_verapdf(output: &UtilityOutput, flavour: &str) -> PdfaValidation {
+    let mut lines = output
+        .stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let compliant = lines.next().is_some_and(|line| line.starts_with("PASS"));
+    PdfaValidation {
+        flavour: flavour.to_string(),
+        compliant,
+        details: lines
+            .take(MAX_PDFA_DETAILS)
+            .map(|line| line.to_string())
+            .collect(),
+    }
+}
+
+async fn validate_pdfa(
+    handle: &tauri::AppHandle,
+    path: &Path,
+    output_profile: OutputProfile,
+) -> Option<PdfaValidation> {
+    if tool_path(handle, Tool::Verapdf).is_err() {
+        println!("veraPDF not found, skipping PDF/A validation");
+        return None;
+    }
+
+    let flavour = output_profile.pdfa_flavour();
+    let args = vec![
+        "--flavour".to_string(),
+        flavour.to_string(),
+        "--format".to_string(),
+        "text".to_string(),
+        "--verbose".to_string(),
+        path.to_string_lossy().to_string(),
+    ];
+    match run_utility(handle, Tool::Verapdf, args, &path.to_string_lossy()).await {
+        Ok(output) => Some(parse_verapdf(&output, flavour)),
+        Err(PipelineError::Failed { output }) if output.code == Some(VERAPDF_INVALID) => {
+            Some(parse_verapdf(&output, flavour))
+        }
+        Err(e) => {
+            println!("Failed to validate PDF/A: {}", e);
+            None
+        }
+    }
+}
+
+/// Checks that a finished document parses, has the pages of the document, each with a text
+/// layer unless it is one of the `blank_pages` of the file, fits the size limit and, when veraPDF
+/// is installed, conforms to its PDF/A profile.
+pub async fn verify_document(
+    handle: &tauri::AppHandle,
+    path: &Path,
+    expected_pages: usize,
+    blank_pages: &[u32],
+    output_profile: OutputProfile,
+    settings: &Settings,
+) -> VerificationReport {
+    let mut failures = Vec::new();
+
+    let file_size = fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    let max_file_size = settings.max_file_size_mb.saturating_mul(1024 * 1024);
+    if file_size == 0 {
+        failures.push("The file is empty".to_string());
+    } else if file_size > max_file_size {
+        failures.push(format!(
+            "The file has {:.1} MB, more than the limit of {} MB",
+            file_size as f64 / (1024.0 * 1024.0),
+            settings.max_file_size_mb
+        ));
+    }
+
+    let (pages, pages_without_text) = match load_pdf(path) {
+        Ok(document) => (
+            Some(document.get_pages().len()),
+            pages_without_text(&document)
+                .into_iter()
+                .filter(|page| !blank_pages.contains(page))
+                .collect(),
+        ),
+        Err(e) => {
+            failures.push(e);
+            (None, Vec::new())
+        }
+    };
+    if let Some(pages) = pages.filter(|pages| *pages != expected_pages) {
+        failures.push(format!(
+            "The file has {} pages instead of {}",
+            pages, expected_pages
+        ));
+    }
+    if !pages_without_text.is_empty() {
+        failures.push(format!(
+            "No text layer on pages {}",
+            pages_without_text
+                .iter()
+                .map(|page| page.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let pdfa = if settings.validate_pdfa && pages.is_some() {
+        validate_pdfa(handle, path, output_profile).await
+    } else {
+        None
+    };
+    if let Some(pdfa) = pdfa.as_ref().filter(|pdfa| !pdfa.compliant) {
+        failures.push(format!(
+            "The file does not conform to PDF/A-{}",
+            pdfa.flavour
+        ));
+    }
+
+    for failure in &failures {
+        println!("Verification of {} failed: {}", path.display(), failure);
+    }
+    VerificationReport {
+        verified_at: Local::now().to_rfc3339(),
+        passed: failures.is_empty(),
+        expected_pages,
+        pages,
+        file_size,
+        max_file_size,
+        pages_without_text,
+        pdfa,
+        failures,
+    }
+}
@@ -0,0 +1,131 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::llm::models::DocumentInfo;
+use crate::llm::read_json_file;
+
+const WORKSPACE_FILE_NAME: &str = "workspace.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageStatus {
+    #[default]
+    Content,
+    Blank,
+    Separator,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageState {
+    /// Detected status of the page, or the one the user chose when `manual` is set.
+    pub status: PageStatus,
+    /// Share of the page covered by ink, between 0.0 and 1.0.
+    pub ink_coverage: Option<f64>,
+    /// Set when the user chose the status, so that detection does not overwrite it.
+    pub manual: bool,
+    /// Clockwise rotation in degrees that makes the page upright, once detected or chosen.
+    pub rotation: Option<u32>,
+    /// Perceptual hash of the page image, as hexadecimal.
+    pub dhash: Option<String>,
+}
+
+/// Per-page state of a scan, kept as `workspace.json` in its `-data` directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub pages: BTreeMap<u32, PageState>,
+}
+
+impl Workspace {
+    /// Blank and separator pages are neither transcribed nor copied into the finished PDF, once
+    /// the user confirmed them. A detected status alone is only a suggestion.
+    pub fn is_skipped(&self, page: u32) -> bool {
+        self.pages
+            .get(&page)
+            .is_some_and(|state| state.manual && state.status != PageStatus::Content)
+    }
+
+    /// Whether a page was detected or marked as blank or separator, even if it is kept.
+    pub fn looks_blank(&self, page: u32) -> bool {
+        self.pages
+            .get(&page)
+            .is_some_and(|state| state.status != PageStatus::Content)
+    }
+
+    pub fn rotation(&self, page: u32) -> u32 {
+        self.pages
+            .get(&page)
+            .and_then(|state| state.rotation)
+            .unwrap_or(0)
+    }
+
+    /// Pairs page image paths with the numbers of their pages, dropping the skipped pages.
+    pub fn content_pages(
+        &self,
+        paths: &[String],
+        pages: &[u32],
+    ) -> Result<Vec<(String, u32)>, String> {
+        if paths.len() != pages.len() {
+            return Err(format!(
+                "Expected a page number for each of the {} page images, got {}",
+                paths.len(),
+                pages.len()
+            ));
+        }
+        Ok(paths
+            .iter()
+            .cloned()
+            .zip(pages.iter().copied())
+            .filter(|(_, page)| !self.is_skipped(*page))
+            .collect())
+    }
+}
+
+/// Number of the page an image or transcription path belongs to (`page-N.jpg`).
+pub fn page_number(path: &str) -> Option<u32> {
+    let re = Regex::new(r"page-(\d+)").expect("Regex should never fail");
+    re.captures(path)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
+pub fn load_workspace(data_dir: &Path) -> Result<Workspace, String> {
+    let path = data_dir.join(WORKSPACE_FILE_NAME);
+    if !path.exists() {
+        return Ok(Workspace::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read workspace: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse workspace: {}", e))
+}
+
+pub fn save_workspace(data_dir: &Path, workspace: &Workspace) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(workspace)
+        .map_err(|e| format!("Failed to serialize workspace: {}", e))?;
+    fs::write(data_dir.join(WORKSPACE_FILE_NAME), content)
+        .map_err(|e| format!("Failed to write workspace: {}", e))
+}
+
+/// Finds the document JSONs under a directory, skipping the JSON files that are not documents.
+pub fn find_documents(dir: &Path) -> Result<Vec<DocumentInfo>, String> {
+    let mut documents = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read directory: {}", e))?
+            .path();
+        if path.is_dir() {
+            documents.extend(find_documents(&path)?);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            if let Ok(document_info) = read_json_file(&path) {
+                documents.push(document_info);
+            }
+        }
+    }
+
+    Ok(documents)
+}
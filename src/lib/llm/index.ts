@@ -1,6 +1,8 @@
-import { Command } from "@tauri-apps/plugin-shell";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Anthropic } from "@anthropic-ai/sdk";
 import { z } from "zod";
+import type { RasterizeProgress, RasterizeReport } from "$lib/types";
 
 const client = new Anthropic();
 
@@ -16,22 +18,20 @@ enum Model {
   Sonnet = "claude-3-5-sonnet-20240620",
 }
 
-export const extractPDFImages = async (pdfPath: string, outputDir: string) => {
-  const command = await Command.create("magick.exe", [
-    "-density",
-    "150",
-    pdfPath,
-    "-resize",
-    "1500x1500",
-    "-scene",
-    "1",
-    "+adjoin",
-
-    outputDir,
-  ]).execute();
-
-  if (command.code !== 0)
-    throw new Error(
-      `magick exited with code ${command.code}. Error output: ${command.stderr}`,
-    );
+export const rasterizePDF = async (
+  pdfPath: string,
+  dataPath: string,
+  onProgress?: (progress: RasterizeProgress) => void,
+) => {
+  const unlisten = await listen<RasterizeProgress>(
+    "rasterize-progress",
+    (event) => {
+      if (event.payload.pdf_path === pdfPath) onProgress?.(event.payload);
+    },
+  );
+  try {
+    return await invoke<RasterizeReport>("rasterize_pdf", { pdfPath, dataPath });
+  } finally {
+    unlisten();
+  }
 };
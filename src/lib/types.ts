@@ -4,7 +4,36 @@ export interface DocumentInfo {
   file_name: string;
   file_name_history: string[];
   pages_paths: string[];
+  pages: number[];
   json_file_path: string;
+  canonical_type: {
+    id: string;
+    type_name: string;
+    type_abbr: string;
+    score: number;
+  } | null;
+  resolved_entities: {
+    id: string;
+    canonical_name: string;
+    short_name: string;
+    matched: string;
+    score: number;
+  }[];
+  amounts: {
+    label: string;
+    key: string | null;
+    value: string;
+    page: number | null;
+  }[];
+  structured_data: ({ type: string } & Record<string, unknown>) | null;
+  validity: {
+    issue_date: string | null;
+    expiry_date: string;
+    source: "extracted" | "rule";
+  } | null;
+  content_hash: string | null;
+  excluded_pages: number[];
+  verification: VerificationReport | null;
   reasoning: {
     document_summary: {
       analysis: string;
@@ -28,9 +57,111 @@ export interface DocumentInfo {
       analysis: string;
       type_abbr: string;
     };
+    identifiers: {
+      analysis: string;
+      cnpj: string;
+      cpf: string;
+      document_number: string;
+    };
+    validity: {
+      analysis: string;
+      issue_date: string;
+      expiry_date: string;
+    };
   };
 }
 
+export interface ArchivedDocument {
+  file_name: string;
+  pdf_path: string;
+  json_file_path: string;
+  content_hash: string | null;
+  page_hashes: string[];
+  archived_at: string;
+}
+
+export type CollisionPolicy = "error" | "suffix" | "overwrite";
+
+export interface CollisionPreview {
+  policy: CollisionPolicy;
+  requested_path: string;
+  collision: boolean;
+  path: string | null;
+  file_name: string | null;
+  overwrites: boolean;
+}
+
+export interface ToolReport {
+  tool: "ocrmypdf" | "magick" | "pdftoppm" | "tesseract" | "verapdf";
+  path: string | null;
+  source: "configured" | "sidecar" | "path" | null;
+  version: string | null;
+  supported: boolean;
+  required: boolean;
+}
+
+export interface EnvironmentReport {
+  tools: ToolReport[];
+  tesseract_languages: string[];
+  missing_languages: string[];
+  api_key_present: boolean;
+  renderer: ToolReport["tool"] | null;
+  ready: boolean;
+  detecting: boolean;
+}
+
+export interface RasterizeProgress {
+  pdf_path: string;
+  page: number;
+  total: number;
+  skipped: boolean;
+}
+
+export interface RasterizeReport {
+  renderer: ToolReport["tool"] | null;
+  total: number;
+  rendered: number;
+  skipped: number;
+}
+
+export interface UtilityOutput {
+  tool: ToolReport["tool"];
+  code: number | null;
+  signal: number | null;
+  stderr_tail: string[];
+  duration_ms: number;
+}
+
+export interface UtilityProgress {
+  tool: ToolReport["tool"];
+  job: string;
+  pages_done: number;
+  total_pages: number | null;
+}
+
+export interface VerificationReport {
+  verified_at: string;
+  passed: boolean;
+  expected_pages: number;
+  pages: number | null;
+  file_size: number;
+  max_file_size: number;
+  pages_without_text: number[];
+  pdfa: {
+    flavour: string;
+    compliant: boolean;
+    details: string[];
+  } | null;
+  failures: string[];
+}
+
+export type PipelineError =
+  | { kind: "spawn"; tool: ToolReport["tool"]; message: string }
+  | { kind: "failed"; output: UtilityOutput }
+  | { kind: "timeout"; timeout_secs: number; output: UtilityOutput }
+  | { kind: "verification"; report: VerificationReport }
+  | { kind: "other"; message: string };
+
 export interface ProcessingPage {
   id: string;
   pages: number[];
@@ -51,6 +182,20 @@ export interface ProcessedDocument extends ProcessingPage {
 export interface FinishedDocument extends ProcessedDocument {
 }
 
+export type PageStatus = "content" | "blank" | "separator";
+
+export interface PageState {
+  status: PageStatus;
+  ink_coverage: number | null;
+  manual: boolean;
+  rotation: number | null;
+  dhash: string | null;
+}
+
+export interface Workspace {
+  pages: Record<string, PageState>;
+}
+
 export interface SetupState {
   path: string | undefined;
   dataPath: string | undefined;
@@ -67,4 +212,5 @@ export interface SetupState {
   confirmProcessDialogOpen: boolean;
   showStatusCanvas: boolean;
   isExtractingImages: boolean;
+  extractionProgress: RasterizeProgress | undefined;
 }